@@ -0,0 +1,190 @@
+//! The `.loxc` format for compiled chunks, so scripts can be run without lexing or compiling.
+//!
+//! All integers are little endian. A file is the magic bytes, a `u16` version and the chunk:
+//!
+//! ```text
+//! code       u32 length, then the bytes
+//! lines      u32 per byte of code
+//! constants  u32 count, then a tag byte per constant followed by its payload:
+//!            0 nil, 1 bool (u8), 2 number (f32), 3 string (u32 length, then utf-8)
+//! ```
+
+use chunks::Chunk;
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+use value::Value;
+use verify::VerifyError;
+
+/// The first bytes of every `.loxc` file
+pub const MAGIC: &[u8; 4] = b"LOXC";
+/// Bumped whenever the layout or the opcodes change, so stale files are rejected
+pub const VERSION: u16 = 2;
+
+const NIL: u8 = 0;
+const BOOL: u8 = 1;
+const NUMBER: u8 = 2;
+const STRING: u8 = 3;
+
+/// The ways a `.loxc` file can fail to load
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadError {
+    /// The input doesn't start with `MAGIC`
+    NotBytecode,
+    /// The file was written by a different version of the format
+    UnsupportedVersion(u16),
+    /// The input ended in the middle of the chunk
+    UnexpectedEnd,
+    UnknownConstant(u8),
+    InvalidString,
+    /// Bytes were left over after the chunk
+    TrailingBytes,
+    /// The chunk was read but its bytecode is malformed
+    Invalid(VerifyError),
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::NotBytecode => write!(f, "Not a compiled lox file"),
+            LoadError::UnsupportedVersion(version) => write!(
+                f,
+                "Bytecode version {} is not supported, expected version {}",
+                version, VERSION
+            ),
+            LoadError::UnexpectedEnd => write!(f, "Unexpected end of the bytecode"),
+            LoadError::UnknownConstant(tag) => write!(f, "Unknown constant tag `{}`", tag),
+            LoadError::InvalidString => write!(f, "A string constant is not valid utf-8"),
+            LoadError::TrailingBytes => write!(f, "Unexpected bytes after the chunk"),
+            LoadError::Invalid(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl ::std::error::Error for LoadError {}
+
+/// Writes a chunk in the `.loxc` format
+pub(crate) fn write<W: Write>(chunk: &Chunk, out: &mut W) -> io::Result<()> {
+    out.write_all(MAGIC)?;
+    out.write_all(&VERSION.to_le_bytes())?;
+
+    write_len(out, chunk.code.len())?;
+    out.write_all(&chunk.code)?;
+
+    for line in chunk.lines.iter() {
+        out.write_all(&line.to_le_bytes())?;
+    }
+
+    write_len(out, chunk.constants.len())?;
+
+    for constant in chunk.constants.iter() {
+        if let Some(string) = constant.try_str() {
+            out.write_all(&[STRING])?;
+            write_len(out, string.len())?;
+            out.write_all(string.as_bytes())?;
+        } else if let Some(number) = constant.try_number() {
+            out.write_all(&[NUMBER])?;
+            out.write_all(&number.to_le_bytes())?;
+        } else if let Some(boolean) = constant.try_bool() {
+            out.write_all(&[BOOL, boolean as u8])?;
+        } else if constant.is_nil() {
+            out.write_all(&[NIL])?;
+        } else {
+            let msg = format!("Cannot serialize the constant `{}`", constant);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+    }
+
+    Ok(())
+}
+
+fn write_len<W: Write>(out: &mut W, len: usize) -> io::Result<()> {
+    out.write_all(&(len as u32).to_le_bytes())
+}
+
+/// Reads a chunk in the `.loxc` format, allocating string constants with `string`
+pub(crate) fn read<F>(bytes: &[u8], mut string: F) -> Result<Chunk, LoadError>
+where
+    F: FnMut(String) -> Value,
+{
+    let mut reader = Reader { bytes };
+
+    if reader.take(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+        return Err(LoadError::NotBytecode);
+    }
+
+    let version = reader.u16()?;
+
+    if version != VERSION {
+        return Err(LoadError::UnsupportedVersion(version));
+    }
+
+    let mut chunk = Chunk::new();
+
+    let len = reader.u32()? as usize;
+    chunk.code = reader.take(len)?.to_vec();
+
+    for _ in 0..len {
+        chunk.lines.push(reader.u32()?);
+    }
+
+    for _ in 0..reader.u32()? {
+        let constant = match reader.u8()? {
+            NIL => Value::nil(),
+            BOOL => Value::bool(reader.u8()? != 0),
+            NUMBER => Value::number(f32::from_bits(reader.u32()?)),
+            STRING => {
+                let len = reader.u32()? as usize;
+                let bytes = reader.take(len)?.to_vec();
+
+                string(String::from_utf8(bytes).map_err(|_| LoadError::InvalidString)?)
+            }
+            tag => return Err(LoadError::UnknownConstant(tag)),
+        };
+
+        chunk.add_constant(constant);
+    }
+
+    if !reader.bytes.is_empty() {
+        return Err(LoadError::TrailingBytes);
+    }
+
+    Ok(chunk)
+}
+
+/// Whether the input looks like a `.loxc` file rather than source
+pub fn is_bytecode(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], LoadError> {
+        if self.bytes.len() < len {
+            return Err(LoadError::UnexpectedEnd);
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, LoadError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, LoadError> {
+        let bytes = self.take(2)?;
+
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, LoadError> {
+        let bytes = self.take(4)?;
+
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
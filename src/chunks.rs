@@ -1,10 +1,11 @@
-#[cfg(feature = "debug")]
-use opcode;
+use op::{OpCode, Operand};
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
 use value::Value;
 
 type Line = u32;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 /// A wrapper around an array of bytes
 pub struct Chunk {
     pub code: Vec<u8>,
@@ -31,64 +32,131 @@ impl Chunk {
         self.lines.push(line)
     }
 
-    #[cfg(feature = "debug")]
-    pub fn disassemble(&self, name: &str) {
-        println!("== {} ==\n", name);
+    /// The disassembly of the chunk, which can be written to any `fmt::Write` or `io::Write`
+    /// with `write!`
+    pub fn disassemble<'a>(&'a self, name: &'a str) -> Disassembly<'a> {
+        Disassembly { chunk: self, name }
+    }
+
+    /// The instruction starting at `offset`
+    pub fn instruction(&self, offset: usize) -> Instruction<'_> {
+        Instruction {
+            chunk: self,
+            offset,
+        }
+    }
+}
+
+/// Every instruction of a chunk, one per line after a header with the chunk's name
+pub struct Disassembly<'a> {
+    chunk: &'a Chunk,
+    name: &'a str,
+}
+
+impl<'a> Display for Disassembly<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "== {} ==", self.name)?;
+
+        let mut offset = 0;
 
-        let mut i = 0;
+        while offset < self.chunk.code.len() {
+            let instruction = self.chunk.instruction(offset);
 
-        while i < self.code.len() {
-            i = self.disassemble_instruction(i);
+            writeln!(f, "{}", instruction)?;
+            offset += instruction.size();
         }
+
+        Ok(())
     }
+}
 
-    #[cfg(feature = "debug")]
-    pub fn disassemble_instruction(&self, offset: usize) -> usize {
-        print!("{:04}", offset);
+/// An instruction displayed as its offset, line, name and operands, with the value of
+/// constant operands. The chunk may be malformed, so missing operands and constants are shown
+/// rather than assumed.
+pub struct Instruction<'a> {
+    chunk: &'a Chunk,
+    offset: usize,
+}
 
-        if offset > 0 && self.lines[offset] == self.lines[offset - 1] {
-            print!("   | ")
-        } else {
-            print!("{:4} ", self.lines[offset])
+impl<'a> Instruction<'a> {
+    /// The number of bytes taken by the opcode and its operands
+    pub fn size(&self) -> usize {
+        let operands = OpCode::try_from(self.chunk.code[self.offset])
+            .map_or(0, |opcode| opcode.operands().len());
+
+        ::std::cmp::min(1 + operands, self.chunk.code.len() - self.offset)
+    }
+
+    pub fn operation(&self) -> Operation<'a> {
+        Operation {
+            chunk: self.chunk,
+            offset: self.offset,
         }
+    }
+}
+
+impl<'a> Display for Instruction<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let chunk = self.chunk;
+        let offset = self.offset;
 
-        let instruction = self.code[offset];
-
-        match instruction {
-            opcode::RETURN => simple_instruction("OPCODE::RETURN", offset),
-            opcode::CONSTANT => self.constant_instruction("OPCODE::CONSTANT", offset),
-            opcode::NEGATE => simple_instruction("OPCODE::NEGATE", offset),
-            opcode::ADD => simple_instruction("OPCODE::ADD", offset),
-            opcode::SUB => simple_instruction("OPCODE::SUB", offset),
-            opcode::DIV => simple_instruction("OPCODE::DIV", offset),
-            opcode::MUL => simple_instruction("OPCODE::MUL", offset),
-            opcode::NIL => simple_instruction("OPCODE::NIL", offset),
-            opcode::TRUE => simple_instruction("OPCODE::TRUE", offset),
-            opcode::FALSE => simple_instruction("OPCODE::FALSE", offset),
-            opcode::NOT => simple_instruction("OPCODE::NOT", offset),
-            opcode::EQUAL => simple_instruction("OPCODE::EQUAL", offset),
-            opcode::LESS => simple_instruction("OPCODE::LESS", offset),
-            opcode::GREATER => simple_instruction("OPCODE:GREATER", offset),
-            _ => {
-                println!("UNKOWN OPCODE {}", instruction);
-                offset + 1
+        write!(f, "{:04} ", offset)?;
+
+        match chunk.lines.get(offset) {
+            Some(line) if offset > 0 && chunk.lines.get(offset - 1) == Some(line) => {
+                write!(f, "   | ")?
             }
+            Some(line) => write!(f, "{:4} ", line)?,
+            None => write!(f, "   ? ")?,
         }
-    }
 
-    #[cfg(feature = "debug")]
-    pub fn constant_instruction(&self, name: &str, offset: usize) -> usize {
-        let constant = self.code[offset + 1];
-        println!(
-            "{:16}{:4} '{}' ",
-            name, constant, self.constants[constant as usize]
-        );
-        offset + 2
+        write!(f, "{}", self.operation())
     }
 }
 
-#[cfg(feature = "debug")]
-pub fn simple_instruction(name: &str, offset: usize) -> usize {
-    println!("{}", name);
-    offset + 1
+/// The name and operands of an instruction, without its offset and line
+pub struct Operation<'a> {
+    chunk: &'a Chunk,
+    offset: usize,
+}
+
+impl<'a> Display for Operation<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let chunk = self.chunk;
+        let offset = self.offset;
+        let instruction = chunk.code[offset];
+
+        let opcode = match OpCode::try_from(instruction) {
+            Ok(opcode) => opcode,
+            Err(_) => return write!(f, "UNKNOWN {:#04x}", instruction),
+        };
+
+        let (name, operands) = (opcode.name(), opcode.operands());
+
+        if operands.is_empty() {
+            return write!(f, "{}", name);
+        }
+
+        write!(f, "{:16}", name)?;
+
+        for (i, &kind) in operands.iter().enumerate() {
+            let operand = match chunk.code.get(offset + 1 + i) {
+                Some(&operand) => operand,
+                None => return write!(f, " <missing operand>"),
+            };
+
+            write!(f, " {:4}", operand)?;
+
+            if kind == Operand::Count {
+                continue;
+            }
+
+            match chunk.constants.get(operand as usize) {
+                Some(constant) => write!(f, " '{}'", constant.to_string().escape_debug())?,
+                None => write!(f, " <invalid constant>")?,
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,302 @@
+//! Stable codes identifying each kind of diagnostic, shown with the message and explained
+//! by `lox --explain`. A code is never reused or renumbered once released
+
+pub const UNCLOSED_STRING: &str = "E0001";
+pub const UNCLOSED_BLOCK_COMMENT: &str = "E0002";
+pub const UNCLOSED_INTERPOLATION: &str = "E0003";
+pub const EMPTY_INTERPOLATION: &str = "E0004";
+pub const UNKNOWN_ESCAPE: &str = "E0005";
+pub const INVALID_UNICODE_ESCAPE: &str = "E0006";
+pub const INVALID_DIGIT: &str = "E0007";
+pub const INVALID_SEPARATOR: &str = "E0008";
+pub const MISSING_DIGITS: &str = "E0009";
+pub const MISSING_EXPONENT: &str = "E0010";
+pub const NUMBER_TOO_LARGE: &str = "E0011";
+pub const UNEXPECTED_CHAR: &str = "E0012";
+pub const UNEXPECTED_EOF: &str = "E0013";
+pub const EXPECTED_EXPRESSION: &str = "E0014";
+pub const EXPECTED_TOKEN: &str = "E0015";
+pub const EXPECTED_NAME: &str = "E0016";
+pub const TOO_MANY_CONSTANTS: &str = "E0017";
+pub const TOO_MANY_ITEMS: &str = "E0018";
+pub const MODULE_NAME: &str = "E0019";
+pub const EQUAL_COMPARISON: &str = "W0001";
+
+const EXPLANATIONS: &[(&str, &str)] = &[
+    (
+        UNCLOSED_STRING,
+        "A string literal was not closed before the end of the input.
+
+Erroneous code example:
+
+    var greeting = \"hello;
+
+Add the closing quote:
+
+    var greeting = \"hello\";
+
+Raw strings `r\"...\"` need a closing quote too.",
+    ),
+    (
+        UNCLOSED_BLOCK_COMMENT,
+        "A `/*` block comment was not closed before the end of the input.
+
+Block comments nest, so every `/*` needs its own `*/`.
+
+Erroneous code example:
+
+    /* outer /* inner */
+    var a = 1;
+
+Close both comments:
+
+    /* outer /* inner */ */
+    var a = 1;",
+    ),
+    (
+        UNCLOSED_INTERPOLATION,
+        "A `${` inside a string was not closed with a `}`.
+
+Erroneous code example:
+
+    \"total: ${count + 1\";
+
+Close the interpolation before the end of the string:
+
+    \"total: ${count + 1}\";",
+    ),
+    (
+        EMPTY_INTERPOLATION,
+        "A `${}` inside a string has no expression to interpolate.
+
+Erroneous code example:
+
+    \"value: ${}\";
+
+Put an expression inside the braces, or escape the `$` to keep the text as is:
+
+    \"value: ${value}\";
+    \"value: \\${}\";",
+    ),
+    (
+        UNKNOWN_ESCAPE,
+        "A backslash in a string is followed by a char that is not a known escape.
+
+The known escapes are `\\n`, `\\t`, `\\r`, `\\0`, `\\\\`, `\\\"`, `\\$` and `\\u{...}`.
+
+Erroneous code example:
+
+    var path = \"C:\\users\";
+
+Escape the backslash, or use a raw string:
+
+    var path = \"C:\\\\users\";
+    var path = r\"C:\\users\";",
+    ),
+    (
+        INVALID_UNICODE_ESCAPE,
+        "A `\\u{...}` escape does not hold a valid unicode scalar value.
+
+It must have one to six hex digits between braces naming a char that is not a surrogate.
+
+Erroneous code example:
+
+    \"\\u{110000}\";
+    \"\\u00e9\";
+
+Use a valid value between braces:
+
+    \"\\u{e9}\";",
+    ),
+    (
+        INVALID_DIGIT,
+        "A number literal contains a digit that is not valid for its base.
+
+Erroneous code example:
+
+    var mask = 0b102;
+    var color = 0xFG;
+
+Binary literals only use `0` and `1`, octal `0` to `7` and hex `0` to `9` and `a` to `f`:
+
+    var mask = 0b101;
+    var color = 0xFF;",
+    ),
+    (
+        INVALID_SEPARATOR,
+        "A `_` in a number literal is not between digits.
+
+Separators go between digits, so they can't be doubled, start a fraction or exponent, or
+end a number or its fraction.
+
+Erroneous code example:
+
+    var million = 1__000_000_;
+    var half = 0._5;
+
+Use single separators between digits:
+
+    var million = 1_000_000;",
+    ),
+    (
+        MISSING_DIGITS,
+        "A `0x`, `0b` or `0o` prefix is not followed by any digits.
+
+Erroneous code example:
+
+    var mask = 0x;
+
+Add the digits of the number:
+
+    var mask = 0xFF;",
+    ),
+    (
+        MISSING_EXPONENT,
+        "The `e` of a number in scientific notation is not followed by an exponent.
+
+Erroneous code example:
+
+    var small = 1.5e-;
+
+Add the digits of the exponent:
+
+    var small = 1.5e-3;",
+    ),
+    (
+        NUMBER_TOO_LARGE,
+        "A hex, binary or octal literal does not fit in 64 bits, or a decimal literal is too
+large to be a number, which is a 32 bit float.
+
+Erroneous code example:
+
+    var big = 0x1_0000_0000_0000_0000;
+    var huge = 1e40;
+
+Use a decimal literal in scientific notation for large numbers, up to about 3.4e38:
+
+    var big = 1.8e19;",
+    ),
+    (
+        UNEXPECTED_CHAR,
+        "The input contains a char that does not start any token.
+
+Erroneous code example:
+
+    var total = price # tax;
+    var width = 10px;
+
+Remove the char or replace it with an operator:
+
+    var total = price + tax;
+    var width = 10;",
+    ),
+    (
+        UNEXPECTED_EOF,
+        "The input ended in the middle of a statement.
+
+Erroneous code example:
+
+    var total = 1 +
+
+Finish the statement:
+
+    var total = 1 + 2;",
+    ),
+    (
+        EXPECTED_EXPRESSION,
+        "A value was expected but the next token can't start an expression.
+
+Erroneous code example:
+
+    var total = 1 + ;
+
+Add the missing operand:
+
+    var total = 1 + 2;",
+    ),
+    (
+        EXPECTED_TOKEN,
+        "A specific token such as a `;` or a closing delimiter was expected but not found.
+
+Erroneous code example:
+
+    var items = [1, 2
+    var count = len(items)
+
+Add the missing tokens:
+
+    var items = [1, 2];
+    var count = len(items);",
+    ),
+    (
+        EXPECTED_NAME,
+        "A name was expected, after `var` or after the `.` of a property access.
+
+Erroneous code example:
+
+    var 1 = 2;
+    Math.1;
+
+Use an identifier:
+
+    var one = 2;
+    Math.PI;",
+    ),
+    (
+        TOO_MANY_CONSTANTS,
+        "A single script uses more than 256 constants.
+
+Each distinct number, string and global name in a script takes a constant slot, and a
+chunk has 256.
+
+Split the script into several smaller ones, or build large tables at runtime.",
+    ),
+    (
+        TOO_MANY_ITEMS,
+        "A call or list literal has more than 255 arguments or items, or a map literal has
+more than 127 entries. The values are all on the stack while the call or literal is
+evaluated, and a map entry takes two of its slots.
+
+Erroneous code example:
+
+    var big = [0, 1, 2, /* ... */ 300];
+
+Build large collections at runtime instead:
+
+    var big = [];
+    big.push(0);",
+    ),
+    (
+        MODULE_NAME,
+        "A module imported with `import \"path\";` is defined as a global named after its
+file, but the file name is not a valid identifier.
+
+Erroneous code example:
+
+    import \"lib/string-utils.lox\";
+
+Import the globals it defines by name instead:
+
+    import { pad, trim } from \"lib/string-utils.lox\";",
+    ),
+    (
+        EQUAL_COMPARISON,
+        "A single `=` is used inside an expression, where it compares its operands.
+
+Erroneous code example:
+
+    var same = a = b;
+
+`==` is the equality operator:
+
+    var same = a == b;",
+    ),
+];
+
+/// The description and example fix of a diagnostic code, e.g. `E0001`
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|&&(known, _)| known.eq_ignore_ascii_case(code))
+        .map(|&(_, explanation)| explanation)
+}
@@ -1,13 +1,16 @@
 // TODO: add an error reporter
 // Pretty printing of errors
 use chunks::Chunk;
-use error::Reporter;
+use codes;
+use error::{Diagnostic, Level, Reporter};
 
 use object::{RawObject, StringObject};
 use opcode;
 use pos::{Span, Spanned};
+use std::borrow::Cow;
 use std::collections::{HashMap, VecDeque};
 use std::fmt::Debug;
+use std::path::Path;
 use token::{RuleToken, Token, TokenType};
 use value::Value;
 
@@ -16,12 +19,24 @@ type ParseResult<T> = Result<T, ()>;
 pub struct Compiler<'a> {
     chunk: Option<Chunk>,
     pub chunks: Vec<Chunk>,
+    /// The doc comment of each global declared after `///` comments
+    pub docs: HashMap<&'a str, String>,
     current_token: Option<Spanned<Token<'a>>>,
     tokens: VecDeque<Spanned<Token<'a>>>,
     pub reporter: Reporter,
-    prefix: HashMap<RuleToken, &'a PrefixParser>,
-    infix: HashMap<RuleToken, &'a InfixParser>,
+    prefix: HashMap<RuleToken, &'a dyn PrefixParser>,
+    infix: HashMap<RuleToken, &'a dyn InfixParser>,
+    /// The line of the current token, which emitted code is attributed to
     line: u32,
+    /// The constant of each string and name used in the current chunk, so each is only
+    /// stored once
+    strings: HashMap<Cow<'a, str>, u8>,
+    /// The constant of each number used in the current chunk by its bits
+    numbers: HashMap<u32, u8>,
+    /// Whether the infix expression being parsed is allowed to be an assignment target
+    can_assign: bool,
+    /// Set after an error until the next statement so errors caused by the first one aren't reported
+    panic_mode: bool,
     ///  A linked list of all the objects allocated. This
     /// is passed to the vm so runtime collection can be done
     pub objects: RawObject,
@@ -68,32 +83,47 @@ impl<'a> Compiler<'a> {
     pub fn new(reporter: Reporter, tokens: Vec<Spanned<Token<'a>>>) -> Self {
         let mut tokens = tokens.into_iter().collect::<VecDeque<_>>();
         let current_token = tokens.pop_front();
+        let line = current_token
+            .as_ref()
+            .map(|token| token.span.start.line)
+            .unwrap_or(1);
 
         let mut compiler = Compiler {
             chunk: Some(Chunk::new()),
             chunks: vec![],
+            docs: HashMap::new(),
             tokens,
             current_token,
             reporter,
             objects: ::std::ptr::null::<RawObject>() as RawObject,
             prefix: HashMap::new(),
             infix: HashMap::new(),
-            line: 0,
+            line,
+            strings: HashMap::new(),
+            numbers: HashMap::new(),
+            can_assign: false,
+            panic_mode: false,
         };
 
         compiler.prefix(RuleToken::Literal, &LiteralParselet);
-        compiler.prefix(RuleToken::Literal, &LiteralParselet);
+        compiler.prefix(RuleToken::Ident, &IdentParselet);
+        compiler.prefix(RuleToken::Interpolation, &InterpolationParselet);
         compiler.prefix(RuleToken::Minus, &UnaryParselet);
         compiler.prefix(RuleToken::Bang, &UnaryParselet);
         compiler.prefix(RuleToken::LParen, &GroupingParselet);
-        
+        compiler.prefix(RuleToken::LBracket, &ListParselet);
+        compiler.prefix(RuleToken::LBrace, &MapParselet);
+        compiler.prefix(RuleToken::Error, &ErrorParselet);
 
         // Infix appers in the middle of an expr
         compiler.infix(RuleToken::Plus, &BinaryParselet(Precedence::Term));
         compiler.infix(RuleToken::Minus, &BinaryParselet(Precedence::Term));
         compiler.infix(RuleToken::Slash, &BinaryParselet(Precedence::Factor));
         compiler.infix(RuleToken::Star, &BinaryParselet(Precedence::Factor));
-        compiler.infix(RuleToken::LBracket,&IndexParselet(Precedence::Call));
+        compiler.infix(RuleToken::LBracket, &IndexParselet(Precedence::Call));
+        compiler.infix(RuleToken::LParen, &CallParselet(Precedence::Call));
+        compiler.infix(RuleToken::Dot, &PropertyParselet(Precedence::Call));
+        compiler.infix(RuleToken::Error, &ErrorParselet);
         compiler.infix(
             RuleToken::Comparison,
             &BinaryParselet(Precedence::Comparison),
@@ -109,29 +139,343 @@ impl<'a> Compiler<'a> {
         self.prefix.insert(ty, parser);
     }
 
-    #[cfg(feature = "debug")]
-    pub fn disassemble(&self) {
-        for chunk in self.chunks.iter() {
-            chunk.disassemble("chunk")
-        }
-    }
-
     pub fn infix<T: InfixParser + 'a>(&mut self, ty: RuleToken, parser: &'a T) {
         self.infix.insert(ty, parser);
     }
 
-    pub fn error(&mut self, msg: String, span: Span) {
-        self.reporter.error(msg, span)
+    pub fn error(&mut self, code: &'static str, msg: String, span: Span) {
+        self.report(Diagnostic::new(Level::Error, msg, span).code(code))
     }
 
+    /// Reports a diagnostic, dropping errors while recovering from an earlier one
+    pub fn report(&mut self, diagnostic: Diagnostic) {
+        if diagnostic.level() == Level::Error {
+            if self.panic_mode {
+                return;
+            }
+
+            self.panic_mode = true;
+        }
+
+        self.reporter.report(diagnostic)
+    }
+
+    /// Compiles every statement, recovering from errors so they are all reported in one pass
     pub fn compile(&mut self) -> ParseResult<()> {
+        let mut has_value = false;
+
+        while self.current()? != &TokenType::EOF {
+            let start = self.current_token().map(|token| token.span);
+
+            match self.declaration() {
+                Ok(value) => {
+                    has_value = value;
+                    self.advance()?;
+                }
+                Err(_) => self.synchronize(start),
+            }
+        }
+
+        if !has_value {
+            self.emit_byte(opcode::NIL);
+        }
+
+        self.end_chunk();
+
+        if self.reporter.has_error() {
+            Err(())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Skips the tokens of the statement an error was found in, stopping after a `;`
+    /// or before a keyword that starts a statement. `start` is the span of the statement's
+    /// first token, which is skipped even if it is a keyword.
+    fn synchronize(&mut self, start: Option<Span>) {
+        self.panic_mode = false;
+
+        loop {
+            let (ty, span) = match self.current_token.as_ref() {
+                Some(token) => (&token.value.ty, token.span),
+                None => return,
+            };
+
+            match *ty {
+                TokenType::EOF => return,
+                TokenType::Semicolon => {
+                    let _ = self.advance();
+                    return;
+                }
+                // The error was found at a keyword, e.g. `1 + var`
+                ref ty if starts_statement(ty) && Some(span) != start => return,
+                _ => (),
+            }
+
+            if self.peek().is_some_and(starts_statement) {
+                let _ = self.advance();
+                return;
+            }
+
+            let _ = self.advance();
+        }
+    }
+
+    /// Compiles a declaration returning true if it left the value of the script on the stack
+    fn declaration(&mut self) -> ParseResult<bool> {
+        match *self.current()? {
+            TokenType::Var => {
+                self.var_declaration()?;
+                Ok(false)
+            }
+            TokenType::Import => {
+                self.import_declaration()?;
+                Ok(false)
+            }
+            _ => self.expression_statement(),
+        }
+    }
+
+    fn var_declaration(&mut self) -> ParseResult<()> {
+        let var = self.advance()?;
+
+        let name = match self.current()? {
+            &TokenType::Ident(name) => name,
+            other => {
+                let msg = format!("Expected a variable name instead found `{}`", other);
+                let span = self.current_token().unwrap().span;
+                self.error(codes::EXPECTED_NAME, msg, span);
+                return Err(());
+            }
+        };
+
+        if !var.value.doc.is_empty() {
+            let doc = var
+                .value
+                .doc
+                .iter()
+                .map(|line| line.strip_prefix(' ').unwrap_or(line))
+                .collect::<Vec<_>>();
+
+            self.docs.insert(name, doc.join("\n"));
+        }
+
+        let global = self.identifier_constant(name)?;
+
+        if self.peek() == Some(&TokenType::Equal) {
+            self.advance()?; // Eats the =
+            self.advance()?;
+            self.expression(Precedence::Assignment)?;
+        } else {
+            self.emit_byte(opcode::NIL);
+        }
+
+        self.check(
+            TokenType::Semicolon,
+            "Expected ';' after a variable declaration",
+        )?;
+
+        self.emit_bytes(opcode::DEFINE_GLOBAL, global);
+
+        Ok(())
+    }
+
+    /// Compiles `import "path";`, which defines the module as a global named after its file,
+    /// or `import { a, b } from "path";`, which defines the module's `a` and `b` as globals
+    fn import_declaration(&mut self) -> ParseResult<()> {
+        self.advance()?; // Eats the import
+
+        if self.current()? != &TokenType::LBrace {
+            let (path, span) = self.module_path()?;
+            let name = match Path::new(path).file_stem().and_then(|stem| stem.to_str()) {
+                Some(name) if is_identifier(name) => name,
+                _ => {
+                    let msg = format!("Cannot name a global after the module `{}`", path);
+                    let diagnostic = Diagnostic::new(Level::Error, msg, span)
+                        .code(codes::MODULE_NAME)
+                        .help(format!(
+                            "import its globals with `import {{ .. }} from \"{}\";`",
+                            path
+                        ));
+
+                    self.report(diagnostic);
+                    return Err(());
+                }
+            };
+
+            self.check(TokenType::Semicolon, "Expected ';' after an import")?;
+
+            let path = self.identifier_constant(path)?;
+            let global = self.identifier_constant(name)?;
+
+            self.emit_bytes(opcode::IMPORT, path);
+            self.emit_bytes(opcode::DEFINE_GLOBAL, global);
+
+            return Ok(());
+        }
+
+        let open = self.current_token().unwrap().span;
+        let mut names = vec![];
+
+        loop {
+            self.advance()?;
+
+            match self.current()? {
+                &TokenType::Ident(name) => names.push(name),
+                // Allows a trailing comma
+                &TokenType::RBrace if !names.is_empty() => break,
+                other => {
+                    let msg = format!("Expected a name to import instead found `{}`", other);
+                    let span = self.current_token().unwrap().span;
+                    self.error(codes::EXPECTED_NAME, msg, span);
+                    return Err(());
+                }
+            }
+
+            if self.peek() == Some(&TokenType::Comma) {
+                self.advance()?;
+            } else {
+                self.check_closing(
+                    TokenType::RBrace,
+                    "Expected '}' after the imported names",
+                    open,
+                )?;
+                break;
+            }
+        }
+
+        self.check(
+            TokenType::Ident("from"),
+            "Expected `from` after the imported names",
+        )?;
+        self.advance()?;
+
+        let (path, _) = self.module_path()?;
+
+        self.check(TokenType::Semicolon, "Expected ';' after an import")?;
+
+        let path = self.identifier_constant(path)?;
+
+        for name in names {
+            let global = self.identifier_constant(name)?;
+
+            self.emit_bytes(opcode::IMPORT, path);
+            self.emit_bytes(opcode::GET_PROPERTY, global);
+            self.emit_bytes(opcode::DEFINE_GLOBAL, global);
+        }
+
+        Ok(())
+    }
+
+    /// The path of an import, which must be a plain string literal
+    fn module_path(&mut self) -> ParseResult<(&'a str, Span)> {
+        let span = self.current_token().unwrap().span;
+
+        match self.current()? {
+            &TokenType::String(Cow::Borrowed(path)) => Ok((path, span)),
+            other => {
+                let msg = format!("Expected a module path instead found `{}`", other);
+                let diagnostic = Diagnostic::new(Level::Error, msg, span)
+                    .code(codes::EXPECTED_TOKEN)
+                    .note("module paths are plain strings without escapes or interpolations");
+
+                self.report(diagnostic);
+                Err(())
+            }
+        }
+    }
+
+    /// The value of the last expression statement in a script is its result
+    fn expression_statement(&mut self) -> ParseResult<bool> {
         self.expression(Precedence::Assignment)?;
         self.check(TokenType::Semicolon, "Expected ';' after an expression")?;
 
-        self.end_chunk();
+        if self.peek() == Some(&TokenType::EOF) {
+            Ok(true)
+        } else {
+            self.emit_byte(opcode::POP);
+            Ok(false)
+        }
+    }
+
+    /// Compiles a parenthesised argument list, the current token being the `(`
+    pub fn arguments(&mut self) -> ParseResult<u8> {
+        let open = self.current_token().unwrap().span;
+        let mut arg_count: usize = 0;
+
+        if self.peek() != Some(&TokenType::RParen) {
+            loop {
+                self.advance()?;
+                self.expression(Precedence::Assignment)?;
+
+                if arg_count == 255 {
+                    let span = self.current_token().unwrap().span;
+                    let msg = "Cannot have more than 255 arguments".into();
+                    self.error(codes::TOO_MANY_ITEMS, msg, span);
+                    return Err(());
+                }
+
+                arg_count += 1;
+
+                if self.peek() == Some(&TokenType::Comma) {
+                    self.advance()?;
+                } else {
+                    break;
+                }
+            }
+        }
+
+        self.check_closing(TokenType::RParen, "Expected ')' after arguments", open)?;
+
+        Ok(arg_count as u8)
+    }
+
+    pub fn identifier_constant(&mut self, name: &'a str) -> ParseResult<u8> {
+        self.string_index(Cow::Borrowed(name))
+    }
+
+    /// Emits a string constant, borrowing from the source unless the lexer had to unescape it
+    pub fn string_constant(&mut self, string: Cow<'a, str>) -> ParseResult<()> {
+        let index = self.string_index(string)?;
+
+        self.emit_bytes(opcode::CONSTANT, index);
         Ok(())
     }
 
+    /// Emits a number constant
+    pub fn number_constant(&mut self, number: f32) -> ParseResult<()> {
+        let index = match self.numbers.get(&number.to_bits()) {
+            Some(&index) => index,
+            None => {
+                let index = self.make_constant(Value::number(number))?;
+                self.numbers.insert(number.to_bits(), index);
+                index
+            }
+        };
+
+        self.emit_bytes(opcode::CONSTANT, index);
+        Ok(())
+    }
+
+    /// The constant of a string, allocating it the first time it's used in the chunk
+    fn string_index(&mut self, string: Cow<'a, str>) -> ParseResult<u8> {
+        if let Some(&index) = self.strings.get(&string) {
+            return Ok(index);
+        }
+
+        let object = match string {
+            Cow::Borrowed(string) => StringObject::new(string, self.objects),
+            Cow::Owned(ref string) => StringObject::from_owned(string.clone(), self.objects),
+        };
+
+        self.objects = object;
+
+        let index = self.make_constant(Value::object(object))?;
+        self.strings.insert(string, index);
+
+        Ok(index)
+    }
+
     pub fn emit_byte(&mut self, byte: u8) {
         self.chunk.as_mut().unwrap().write(byte, self.line)
     }
@@ -142,20 +486,13 @@ impl<'a> Compiler<'a> {
         self.emit_byte(byte2);
     }
 
-    pub fn emit_constant(&mut self, constant: Value) -> ParseResult<()> {
-        let value = self.make_constant(constant)?;
-        self.emit_bytes(opcode::CONSTANT, value);
-        Ok(())
-    }
-
     pub fn make_constant(&mut self, value: Value) -> ParseResult<u8> {
         let index = self.chunk.as_mut().unwrap().add_constant(value);
 
-        if index > 256 {
-            self.reporter.error(
-                "Too many constants in one chunk",
-                self.current_token.as_ref().unwrap().span,
-            );
+        if index > 255 {
+            let span = self.current_token.as_ref().unwrap().span;
+            let msg = "Too many constants in one chunk".into();
+            self.error(codes::TOO_MANY_CONSTANTS, msg, span);
             Err(())
         } else {
             Ok(index as u8)
@@ -169,14 +506,23 @@ impl<'a> Compiler<'a> {
 
         self.chunks.push(current_chunk);
         self.chunk = Some(Chunk::new());
+        self.strings.clear();
+        self.numbers.clear();
     }
 
     pub fn advance(&mut self) -> ParseResult<Spanned<Token<'a>>> {
         match self.current_token.take() {
             Some(token) => {
-                self.line = token.span.start.line;
                 self.current_token = self.tokens.pop_front();
 
+                // The final return belongs to the last line of code rather than the end
+                match self.current_token {
+                    Some(ref current) if current.value.ty != TokenType::EOF => {
+                        self.line = current.span.start.line
+                    }
+                    _ => (),
+                }
+
                 Ok(token)
             }
             None => eof_error!(self),
@@ -184,18 +530,36 @@ impl<'a> Compiler<'a> {
     }
 
     pub fn check(&mut self, ty: TokenType<'a>, msg: &str) -> ParseResult<()> {
+        self.expect(ty, msg, None)
+    }
+
+    /// Checks for the delimiter closing the one at `open`, pointing back at it when it is missing
+    pub fn check_closing(&mut self, ty: TokenType<'a>, msg: &str, open: Span) -> ParseResult<()> {
+        self.expect(ty, msg, Some(open))
+    }
+
+    fn expect(&mut self, ty: TokenType<'a>, msg: &str, open: Option<Span>) -> ParseResult<()> {
         if self.peek() == Some(&ty) {
             self.advance()?;
             Ok(())
+        } else if let Ok(&TokenType::Error(_)) = self.current() {
+            // Whatever follows malformed input is most likely caused by it
+            Err(())
         } else {
-            self.reporter.error(
-                msg,
-                *self
-                    .tokens
-                    .front()
-                    .map(|spanned| &spanned.span)
-                    .unwrap_or(&self.reporter.end()),
-            );
+            let span = self
+                .tokens
+                .front()
+                .map(|spanned| spanned.span)
+                .unwrap_or_else(|| self.reporter.end());
+
+            let mut diagnostic =
+                Diagnostic::new(Level::Error, msg, span).code(codes::EXPECTED_TOKEN);
+
+            if let Some(open) = open {
+                diagnostic = diagnostic.label(open, "unclosed delimiter");
+            }
+
+            self.report(diagnostic);
             Err(())
         }
     }
@@ -209,7 +573,7 @@ impl<'a> Compiler<'a> {
 
         match &token {
             Some(&Spanned {
-                value: Token { ref ty },
+                value: Token { ref ty, .. },
                 ..
             }) => Ok(ty),
             None => eof_error!(self),
@@ -226,40 +590,36 @@ impl<'a> Compiler<'a> {
         let token = self.current()?;
         let mut rule = token.rule();
 
-        let parser = self.prefix.get(&rule);
-
-        let parser = if parser.is_none() {
-            let token = self.current_token();
-
-            if token.is_none() {
-                return eof_error!(self);
-            } else {
-                let token = token.unwrap();
-                let span = token.span;
-                let msg = format!("Expected an expression instead found `{}` ", token.value.ty);
-                self.reporter.error(msg, span);
-                return Err(());
-            }
-        } else {
-            parser.unwrap()
+        let parser = match self.prefix.get(&rule) {
+            Some(parser) => parser,
+            None => match self.current_token() {
+                Some(token) => {
+                    let span = token.span;
+                    let msg = format!("Expected an expression instead found `{}` ", token.value.ty);
+                    self.error(codes::EXPECTED_EXPRESSION, msg, span);
+                    return Err(());
+                }
+                None => return eof_error!(self),
+            },
         };
 
         parser.parse(self)?;
 
+        let can_assign = precedence <= Precedence::Assignment;
+
         while precedence <= self.get_precedence() {
             {
                 let token = self.peek().expect("Expected a token");
 
                 rule = token.rule();
             }
-            let parser = self.infix.get(&rule);
-
-            let parser = if parser.is_some() {
-                parser.unwrap()
-            } else {
-                return Ok(());
+            let parser = match self.infix.get(&rule) {
+                Some(parser) => parser,
+                None => return Ok(()),
             };
 
+            self.can_assign = can_assign;
+
             parser.parse(self)?;
         }
 
@@ -271,42 +631,37 @@ impl<'a> Compiler<'a> {
 
         let rule = token.rule();
 
-        let parser = self.infix.get(&rule);
-
-        let parser = if parser.is_some() {
-            parser.unwrap()
-        } else {
-            return Precedence::None;
-        };
-
-        parser.pred()
+        match self.infix.get(&rule) {
+            Some(parser) => parser.pred(),
+            None => Precedence::None,
+        }
     }
 
     pub fn get_op_ty(&self) -> ParseResult<Operator> {
-        match self.current()? {
-            &TokenType::Minus => Ok(Operator::Minus),
-            &TokenType::Plus => Ok(Operator::Plus),
-            &TokenType::Star => Ok(Operator::Star),
-            &TokenType::Slash => Ok(Operator::Slash),
-            &TokenType::Equal => Ok(Operator::Equal),
-            &TokenType::EqualEqual => Ok(Operator::EqualEqual),
-            &TokenType::BangEqual => Ok(Operator::BangEqual),
-            &TokenType::Less => Ok(Operator::Less),
-            &TokenType::Greater => Ok(Operator::Greater),
-            &TokenType::LessEqual => Ok(Operator::LessEqual),
-            &TokenType::GreaterEqual => Ok(Operator::GreaterEqual),
+        match *self.current()? {
+            TokenType::Minus => Ok(Operator::Minus),
+            TokenType::Plus => Ok(Operator::Plus),
+            TokenType::Star => Ok(Operator::Star),
+            TokenType::Slash => Ok(Operator::Slash),
+            TokenType::Equal => Ok(Operator::Equal),
+            TokenType::EqualEqual => Ok(Operator::EqualEqual),
+            TokenType::BangEqual => Ok(Operator::BangEqual),
+            TokenType::Less => Ok(Operator::Less),
+            TokenType::Greater => Ok(Operator::Greater),
+            TokenType::LessEqual => Ok(Operator::LessEqual),
+            TokenType::GreaterEqual => Ok(Operator::GreaterEqual),
             _ => Err(()),
         }
     }
 
-    pub fn get_un_op(&self) -> Result<UnaryOperator, ()> {
+    pub fn get_un_op(&mut self) -> Result<UnaryOperator, ()> {
         match self.current()? {
             &TokenType::Minus => Ok(UnaryOperator::Negate),
             &TokenType::Bang => Ok(UnaryOperator::Bang),
-            ref other => {
-                let token = self.current_token().unwrap();
-                self.reporter
-                    .error(format!("Expected `!` or `-` found {}", other), token.span);
+            other => {
+                let msg = format!("Expected `!` or `-` found {}", other);
+                let span = self.current_token().unwrap().span;
+                self.error(codes::EXPECTED_EXPRESSION, msg, span);
                 Err(())
             }
         }
@@ -348,34 +703,39 @@ impl PrefixParser for LiteralParselet {
 
         match parser.current_token() {
             Some(&Spanned {
-                value: Token {
-                    ty: TokenType::Number(ref num),
-                },
+                value:
+                    Token {
+                        ty: TokenType::Number(ref num),
+                        ..
+                    },
                 ..
-            }) => {
-                parser.emit_constant(Value::number(*num))?;
-                Ok(())
-            }
+            }) => parser.number_constant(*num),
             Some(&Spanned {
-                value: Token {
-                    ty: TokenType::True,
-                },
+                value:
+                    Token {
+                        ty: TokenType::True,
+                        ..
+                    },
                 ..
             }) => {
                 parser.emit_byte(opcode::TRUE);
                 Ok(())
             }
             Some(&Spanned {
-                value: Token {
-                    ty: TokenType::False,
-                },
+                value:
+                    Token {
+                        ty: TokenType::False,
+                        ..
+                    },
                 ..
             }) => {
                 parser.emit_byte(opcode::FALSE);
                 Ok(())
             }
             Some(&Spanned {
-                value: Token { ty: TokenType::Nil },
+                value: Token {
+                    ty: TokenType::Nil, ..
+                },
                 ..
             }) => {
                 parser.emit_byte(opcode::NIL);
@@ -386,23 +746,20 @@ impl PrefixParser for LiteralParselet {
                 value:
                     Token {
                         ty: TokenType::String(ref string),
+                        ..
                     },
                 ..
             }) => {
-                let object = StringObject::new(string, parser.objects);
+                let string = string.clone();
 
-                parser.objects = object;
-
-                parser.emit_constant(Value::object(object))?;
-
-                Ok(())
+                parser.string_constant(string)
             }
-            Some(ref e) => {
+            Some(e) => {
                 let msg = format!(
                     "Expected `{{int}}` or `{{nil}}` or `{{true|false}}` or `{{ident}}` or `{{string}}` found `{}` ",
                     e.value.ty
                 );
-                parser.error(msg, e.span);
+                parser.error(codes::EXPECTED_EXPRESSION, msg, e.span);
                 Err(())
             }
             None => eof_error!(parser),
@@ -410,6 +767,55 @@ impl PrefixParser for LiteralParselet {
     }
 }
 
+/// Compiles `"a ${b} c"` into `"a " + str(b) + " c"`
+#[derive(Debug)]
+pub struct InterpolationParselet;
+
+impl PrefixParser for InterpolationParselet {
+    fn parse(&self, parser: &mut Compiler) -> ParseResult<()> {
+        let part = match *parser.current()? {
+            TokenType::Interpolation(ref part) => part.clone(),
+            _ => unreachable!(),
+        };
+
+        parser.string_constant(part)?;
+
+        loop {
+            parser.advance()?;
+            parser.expression(Precedence::Assignment)?;
+
+            parser.emit_bytes(opcode::TO_STRING, opcode::ADD);
+
+            parser.advance()?;
+
+            let (part, last) = match *parser.current()? {
+                TokenType::Interpolation(ref part) => (part.clone(), false),
+                TokenType::InterpolationEnd(ref part) => (part.clone(), true),
+                // The lexer already reported the unclosed string or interpolation
+                TokenType::Error(_) | TokenType::EOF => return Err(()),
+                ref other => {
+                    let msg = format!(
+                        "Expected `}}` to close the interpolation instead found `{}`",
+                        other
+                    );
+                    let span = parser.current_token().unwrap().span;
+                    parser.error(codes::EXPECTED_TOKEN, msg, span);
+                    return Err(());
+                }
+            };
+
+            if !part.is_empty() {
+                parser.string_constant(part)?;
+                parser.emit_byte(opcode::ADD);
+            }
+
+            if last {
+                return Ok(());
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct UnaryParselet;
 
@@ -449,6 +855,20 @@ impl InfixParser for BinaryParselet {
 
         let op = parser.get_op_ty()?;
 
+        if op == Operator::Equal {
+            let span = parser.current_token().unwrap().span;
+
+            parser.report(
+                Diagnostic::new(
+                    Level::Warning,
+                    "`=` compares the values on either side",
+                    span,
+                )
+                .code(codes::EQUAL_COMPARISON)
+                .suggestion(span, "==", "did you mean `==`?"),
+            );
+        }
+
         parser.advance()?;
 
         parser.expression(self.pred().higher())?; // Compile the rhs
@@ -476,14 +896,274 @@ pub struct GroupingParselet;
 
 impl PrefixParser for GroupingParselet {
     fn parse(&self, parser: &mut Compiler) -> ParseResult<()> {
+        let open = parser.current_token().unwrap().span;
+
         parser.advance()?; //Eats the (
         parser.expression(Precedence::Assignment)?;
 
-        parser.check(TokenType::RParen, "Expected ')'")?;
+        parser.check_closing(TokenType::RParen, "Expected ')'", open)?;
         Ok(())
     }
 }
 
+#[derive(Debug)]
+pub struct ListParselet;
+
+impl PrefixParser for ListParselet {
+    fn parse(&self, parser: &mut Compiler) -> ParseResult<()> {
+        let mut count: usize = 0;
+
+        // The current token is the [
+        let open = parser.current_token().unwrap().span;
+        while parser.peek() != Some(&TokenType::RBracket) {
+            parser.advance()?;
+            parser.expression(Precedence::Assignment)?;
+
+            if count == 255 {
+                let span = parser.current_token().unwrap().span;
+                let msg = "Cannot have more than 255 items in a list literal".into();
+                parser.error(codes::TOO_MANY_ITEMS, msg, span);
+                return Err(());
+            }
+
+            count += 1;
+
+            if parser.peek() == Some(&TokenType::Comma) {
+                parser.advance()?;
+            } else {
+                break;
+            }
+        }
+
+        parser.check_closing(TokenType::RBracket, "Expected ']' after list items", open)?;
+
+        parser.emit_bytes(opcode::BUILD_LIST, count as u8);
+
+        Ok(())
+    }
+}
+
+/// Lox has no blocks, so a `{` starting a statement is a map too
+#[derive(Debug)]
+pub struct MapParselet;
+
+/// Each entry takes two stack slots while the map is built
+const MAX_MAP_ENTRIES: usize = 127;
+
+impl PrefixParser for MapParselet {
+    fn parse(&self, parser: &mut Compiler) -> ParseResult<()> {
+        let mut count: usize = 0;
+
+        // The current token is the {
+        let open = parser.current_token().unwrap().span;
+        while parser.peek() != Some(&TokenType::RBrace) {
+            parser.advance()?;
+            parser.expression(Precedence::Assignment)?;
+
+            parser.check(TokenType::Colon, "Expected ':' after a map key")?;
+
+            parser.advance()?;
+            parser.expression(Precedence::Assignment)?;
+
+            if count == MAX_MAP_ENTRIES {
+                let span = parser.current_token().unwrap().span;
+                let msg = format!(
+                    "Cannot have more than {} entries in a map literal",
+                    MAX_MAP_ENTRIES
+                );
+                parser.error(codes::TOO_MANY_ITEMS, msg, span);
+                return Err(());
+            }
+
+            count += 1;
+
+            if parser.peek() == Some(&TokenType::Comma) {
+                parser.advance()?;
+            } else {
+                break;
+            }
+        }
+
+        parser.check_closing(TokenType::RBrace, "Expected '}' after map entries", open)?;
+
+        parser.emit_bytes(opcode::BUILD_MAP, count as u8);
+
+        Ok(())
+    }
+}
 
 #[derive(Debug)]
 pub struct IndexParselet(pub Precedence);
+
+impl InfixParser for IndexParselet {
+    fn pred(&self) -> Precedence {
+        self.0
+    }
+
+    fn parse(&self, parser: &mut Compiler) -> ParseResult<()> {
+        let can_assign = parser.can_assign;
+
+        parser.advance()?; // Eats the [
+
+        let open = parser.current_token().unwrap().span;
+
+        parser.advance()?;
+
+        parser.expression(Precedence::Assignment)?;
+
+        parser.check_closing(TokenType::RBracket, "Expected ']'", open)?;
+
+        if can_assign && parser.peek() == Some(&TokenType::Equal) {
+            parser.advance()?; // Eats the =
+            parser.advance()?;
+
+            parser.expression(Precedence::Assignment)?;
+
+            parser.emit_byte(opcode::INDEX_SET);
+        } else {
+            parser.emit_byte(opcode::INDEX_GET);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct IdentParselet;
+
+impl PrefixParser for IdentParselet {
+    fn parse(&self, parser: &mut Compiler) -> ParseResult<()> {
+        let name = match parser.current()? {
+            &TokenType::Ident(name) => name,
+            _ => unreachable!(),
+        };
+
+        let global = parser.identifier_constant(name)?;
+
+        parser.emit_bytes(opcode::GET_GLOBAL, global);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct CallParselet(pub Precedence);
+
+impl InfixParser for CallParselet {
+    fn pred(&self) -> Precedence {
+        self.0
+    }
+
+    fn parse(&self, parser: &mut Compiler) -> ParseResult<()> {
+        parser.advance()?; // Eats the (
+
+        let arg_count = parser.arguments()?;
+
+        parser.emit_bytes(opcode::CALL, arg_count);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct PropertyParselet(pub Precedence);
+
+impl InfixParser for PropertyParselet {
+    fn pred(&self) -> Precedence {
+        self.0
+    }
+
+    fn parse(&self, parser: &mut Compiler) -> ParseResult<()> {
+        parser.advance()?; // Eats the .
+        parser.advance()?;
+
+        let name = match *parser.current()? {
+            TokenType::Ident(name) => name,
+            ref other => {
+                let msg = format!(
+                    "Expected a property name after `.` instead found `{}`",
+                    other
+                );
+                let span = parser.current_token().unwrap().span;
+                parser.error(codes::EXPECTED_NAME, msg, span);
+                return Err(());
+            }
+        };
+
+        let name = parser.identifier_constant(name)?;
+
+        if parser.peek() == Some(&TokenType::LParen) {
+            parser.advance()?; // Eats the (
+
+            let arg_count = parser.arguments()?;
+
+            parser.emit_bytes(opcode::INVOKE, name);
+            parser.emit_byte(arg_count);
+        } else {
+            parser.emit_bytes(opcode::GET_PROPERTY, name);
+        }
+
+        Ok(())
+    }
+}
+
+/// Stands in for malformed input the lexer has already reported, so that parsing can carry on past it
+#[derive(Debug)]
+pub struct ErrorParselet;
+
+impl PrefixParser for ErrorParselet {
+    fn parse(&self, parser: &mut Compiler) -> ParseResult<()> {
+        parser.emit_byte(opcode::NIL);
+
+        Ok(())
+    }
+}
+
+/// Malformed input after an operand is parsed like an unknown binary operator
+impl InfixParser for ErrorParselet {
+    fn pred(&self) -> Precedence {
+        Precedence::Term
+    }
+
+    fn parse(&self, parser: &mut Compiler) -> ParseResult<()> {
+        parser.advance()?; // Eats the error
+
+        let starts_expression = parser
+            .peek()
+            .is_some_and(|ty| parser.prefix.contains_key(&ty.rule()));
+
+        if starts_expression {
+            parser.advance()?;
+            parser.expression(self.pred().higher())?;
+            parser.emit_byte(opcode::POP);
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether the token is a keyword that starts a statement, where error recovery resumes
+fn starts_statement(ty: &TokenType) -> bool {
+    matches!(
+        *ty,
+        TokenType::Var
+            | TokenType::Fun
+            | TokenType::Class
+            | TokenType::If
+            | TokenType::While
+            | TokenType::For
+            | TokenType::Print
+            | TokenType::Return
+            | TokenType::Import
+    )
+}
+
+/// Whether a module's file name can name a global
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|first| first.is_alphabetic() || first == '_')
+        && chars.all(|ch| ch.is_alphanumeric() || ch == '_')
+}
@@ -1,123 +1,616 @@
-use pos::Span;
+use bytecode::LoadError;
+use libc;
 use pos::EMPTYSPAN;
-use std::cell::RefCell;
+use pos::{Position, SourceMap, Span};
+use std::cell::{Cell, RefCell};
 use std::fmt::{self, Display};
+use std::io::{self, Write};
 use std::rc::Rc;
+use std::str::FromStr;
 
+/// When diagnostics are printed with ANSI colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Always,
+    Never,
+    /// Only when stderr is a terminal
+    Auto,
+}
+
+/// Collects diagnostics; clones share the same diagnostics and settings
 #[derive(Debug, Clone)]
 pub struct Reporter {
     diagnostics: Rc<RefCell<Vec<Diagnostic>>>,
-    end: Span,
+    end: Rc<Cell<Span>>,
+    deny_warnings: Rc<Cell<bool>>,
 }
-#[derive(Debug)]
+
+#[derive(Debug, Clone)]
 pub struct Diagnostic {
     msg: String,
     span: Span,
     level: Level,
+    /// The stable code of this kind of diagnostic, see `codes`
+    code: Option<&'static str>,
+    /// Secondary spans pointing at related code
+    labels: Vec<Label>,
+    /// Notes and help messages shown after the snippet
+    children: Vec<(Level, String)>,
+    suggestions: Vec<Suggestion>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub msg: String,
+}
+
+/// A fix for a diagnostic that replaces the code in `span`
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub span: Span,
+    pub replacement: String,
+    pub msg: String,
+}
+
+/// An error raised by the vm while it is running a chunk
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub msg: String,
+    /// The name of the file that was running in the source map, empty until the vm raises it
+    pub path: String,
+    pub line: u32,
+}
+
+/// The ways evaluating a piece of source can fail
+#[derive(Debug)]
+pub enum Error {
+    /// The source failed to lex or compile; the reporter holds the diagnostics
+    Compile(Reporter),
+    Runtime(RuntimeError),
+    /// The compiled chunk is malformed or from another version
+    Load(LoadError),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Level {
     Error,
+    Warning,
+    Note,
+    Help,
 }
 
 impl Display for Level {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Level::Error => write!(f, "error"),
+            Level::Warning => write!(f, "warning"),
+            Level::Note => write!(f, "note"),
+            Level::Help => write!(f, "help"),
+        }
+    }
+}
+
+impl RuntimeError {
+    pub fn new<T: Into<String>>(msg: T) -> Self {
+        RuntimeError {
+            msg: msg.into(),
+            path: String::new(),
+            line: 0,
+        }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "[line {}] error: {}", self.line, self.msg)
+        } else {
+            write!(f, "error: {}\n  --> {}:{}", self.msg, self.path, self.line)
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Compile(_) => write!(f, "compile error"),
+            Error::Runtime(ref e) => write!(f, "{}", e),
+            Error::Load(ref e) => write!(f, "error: {}", e),
+        }
+    }
+}
+
+impl ::std::error::Error for RuntimeError {}
+
+impl ::std::error::Error for Error {}
+
+impl ColorChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => unsafe { libc::isatty(libc::STDERR_FILENO) == 1 },
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            "auto" => Ok(ColorChoice::Auto),
+            other => Err(format!(
+                "Unknown color choice `{}`, expected `always`, `never` or `auto`",
+                other
+            )),
         }
     }
 }
 
+impl Default for Reporter {
+    fn default() -> Self {
+        Reporter::new()
+    }
+}
+
 impl Reporter {
     pub fn new() -> Reporter {
         Self {
             diagnostics: Rc::new(RefCell::new(Vec::new())),
-            end: EMPTYSPAN,
+            end: Rc::new(Cell::new(EMPTYSPAN)),
+            deny_warnings: Rc::new(Cell::new(false)),
         }
     }
 
+    /// Whether an error was reported, denied warnings being reported as errors
     pub fn has_error(&self) -> bool {
+        self.diagnostics
+            .borrow()
+            .iter()
+            .any(|diagnostic| diagnostic.level == Level::Error)
+    }
+
+    pub fn has_diagnostics(&self) -> bool {
         !self.diagnostics.borrow().is_empty()
     }
 
+    /// Every diagnostic reported so far, in the order they were reported
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.borrow().clone()
+    }
+
+    /// Turns every warning reported from now on into an error
+    pub fn deny_warnings(&self, deny: bool) {
+        self.deny_warnings.set(deny)
+    }
+
     pub fn set_end(&mut self, span: Span) {
-        self.end = span;
+        self.end.set(span);
     }
 
     pub fn end(&self) -> Span {
-        self.end
+        self.end.get()
     }
 
     pub fn error<T: Into<String>>(&self, msg: T, span: Span) {
-        self.diagnostics.borrow_mut().push(Diagnostic {
+        self.report(Diagnostic::new(Level::Error, msg, span))
+    }
+
+    pub fn warn<T: Into<String>>(&self, msg: T, span: Span) {
+        self.report(Diagnostic::new(Level::Warning, msg, span))
+    }
+
+    pub fn report(&self, mut diagnostic: Diagnostic) {
+        if diagnostic.level == Level::Warning && self.deny_warnings.get() {
+            diagnostic.level = Level::Error;
+            diagnostic
+                .children
+                .push((Level::Note, "warnings are denied".into()));
+        }
+
+        self.diagnostics.borrow_mut().push(diagnostic)
+    }
+
+    /// Prints every diagnostic to stderr as a line of JSON, for editors and CI to parse
+    pub fn emit_json(&self, sources: &SourceMap) {
+        let stderr = io::stderr();
+        let mut out = stderr.lock();
+
+        for diagnostic in self.diagnostics.borrow().iter() {
+            let file = sources.name(diagnostic.span.file);
+            let _ = writeln!(out, "{}", diagnostic.to_json(Some(file)));
+        }
+    }
+
+    /// Prints every diagnostic to stderr with a snippet of the file it points into
+    pub fn emit(&self, sources: &SourceMap, color: ColorChoice) {
+        let stderr = io::stderr();
+        let mut out = stderr.lock();
+        let color = color.enabled();
+
+        for diagnostic in self.diagnostics.borrow().iter() {
+            let _ = print(&mut out, sources, diagnostic, color);
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn new<T: Into<String>>(level: Level, msg: T, span: Span) -> Self {
+        Diagnostic {
             msg: msg.into(),
             span,
-            level: Level::Error,
-        })
+            level,
+            code: None,
+            labels: Vec::new(),
+            children: Vec::new(),
+            suggestions: Vec::new(),
+        }
     }
 
-    pub fn emit(&self, input: &str) {
-        for diagnostic in self.diagnostics.borrow().iter() {
-            print(input, diagnostic)
+    pub fn code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn label<T: Into<String>>(mut self, span: Span, msg: T) -> Self {
+        self.labels.push(Label {
+            span,
+            msg: msg.into(),
+        });
+        self
+    }
+
+    pub fn note<T: Into<String>>(mut self, msg: T) -> Self {
+        self.children.push((Level::Note, msg.into()));
+        self
+    }
+
+    pub fn help<T: Into<String>>(mut self, msg: T) -> Self {
+        self.children.push((Level::Help, msg.into()));
+        self
+    }
+
+    /// Suggests replacing the code in `span` with `replacement`, e.g. "did you mean `==`?"
+    pub fn suggestion<T: Into<String>, U: Into<String>>(
+        mut self,
+        span: Span,
+        replacement: U,
+        msg: T,
+    ) -> Self {
+        self.suggestions.push(Suggestion {
+            span,
+            replacement: replacement.into(),
+            msg: msg.into(),
+        });
+        self
+    }
+
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    pub fn error_code(&self) -> Option<&'static str> {
+        self.code
+    }
+
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn labels(&self) -> &[Label] {
+        &self.labels
+    }
+
+    pub fn children(&self) -> &[(Level, String)] {
+        &self.children
+    }
+
+    pub fn suggestions(&self) -> &[Suggestion] {
+        &self.suggestions
+    }
+
+    /// Serializes the diagnostic as a single line JSON object
+    pub fn to_json(&self, file: Option<&str>) -> String {
+        let labels = self
+            .labels
+            .iter()
+            .map(|label| {
+                format!(
+                    "{{\"message\":{},\"span\":{}}}",
+                    json_string(&label.msg),
+                    json_span(label.span)
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let children = self
+            .children
+            .iter()
+            .map(|&(level, ref msg)| {
+                format!(
+                    "{{\"level\":\"{}\",\"message\":{}}}",
+                    level,
+                    json_string(msg)
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let suggestions = self
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                format!(
+                    "{{\"message\":{},\"replacement\":{},\"span\":{}}}",
+                    json_string(&suggestion.msg),
+                    json_string(&suggestion.replacement),
+                    json_span(suggestion.span)
+                )
+            })
+            .collect::<Vec<_>>();
+
+        format!(
+            "{{\"level\":\"{}\",\"code\":{},\"message\":{},\"file\":{},\"span\":{},\"labels\":[{}],\"children\":[{}],\"suggestions\":[{}]}}",
+            self.level,
+            self.code.map_or("null".into(), json_string),
+            json_string(&self.msg),
+            file.map_or("null".into(), json_string),
+            json_span(self.span),
+            labels.join(","),
+            children.join(","),
+            suggestions.join(",")
+        )
+    }
+}
+
+fn json_span(span: Span) -> String {
+    format!(
+        "{{\"start\":{},\"end\":{}}}",
+        json_position(span.start),
+        json_position(span.end)
+    )
+}
+
+fn json_position(pos: Position) -> String {
+    format!(
+        "{{\"line\":{},\"column\":{},\"absolute\":{}}}",
+        pos.line, pos.column, pos.absolute
+    )
+}
+
+/// Quotes a string for JSON, escaping quotes, backslashes and control chars
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+
+    json.push('"');
+
+    for ch in s.chars() {
+        match ch {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => json.push(ch),
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+/// Prints the diagnostic with a `path:line:col` header and a snippet of the source marking
+/// the primary span with `^` and each label with `-` followed by its message.
+/// Labels in other files are printed as notes
+pub fn print<W: Write>(
+    out: &mut W,
+    sources: &SourceMap,
+    d: &Diagnostic,
+    color: bool,
+) -> io::Result<()> {
+    let input = sources.source(d.span.file);
+    let style = Style(color);
+    let gutter = style.paint(BLUE, "|");
+
+    let level = match d.code {
+        Some(code) => format!("{}[{}]", d.level, code),
+        None => d.level.to_string(),
+    };
+
+    writeln!(
+        out,
+        "{}{}",
+        style.paint(d.level.color(), &level),
+        style.paint(BOLD, &format!(": {}", d.msg))
+    )?;
+
+    writeln!(
+        out,
+        "  {} {}",
+        style.paint(BLUE, "-->"),
+        location(sources, d.span)
+    )?;
+
+    let mut marks = vec![(d.span, '^', "")];
+    marks.extend(
+        d.labels
+            .iter()
+            .filter(|label| label.span.file == d.span.file)
+            .map(|label| (label.span, '-', label.msg.as_str())),
+    );
+    marks.sort_by_key(|&(span, _, _)| (span.start.line, span.start.column));
+
+    let first_line = marks
+        .iter()
+        .map(|mark| mark.0.start.line)
+        .min()
+        .unwrap_or(1);
+    let last_line = marks.iter().map(|mark| mark.0.end.line).max().unwrap_or(1);
+
+    for (idx, line) in input
+        .lines()
+        .enumerate()
+        .skip(first_line.saturating_sub(4) as usize)
+    {
+        let line_idx = idx as u32 + 1;
+        let number = format!("{:>4}", line_idx);
+
+        writeln!(out, "{} {} {}", style.paint(BLUE, &number), gutter, line)?;
+
+        for &(span, mark, msg) in &marks {
+            if let Some(marker) = underline(span, line_idx, line, mark) {
+                let color = if mark == '^' { d.level.color() } else { BLUE };
+
+                let marker = if msg.is_empty() || line_idx != span.end.line {
+                    marker
+                } else {
+                    format!("{} {}", marker, msg)
+                };
+
+                writeln!(out, "     {} {}", gutter, style.paint(color, &marker))?;
+            }
+        }
+
+        if line_idx >= last_line + 3 {
+            break;
+        }
+    }
+
+    for label in d
+        .labels
+        .iter()
+        .filter(|label| label.span.file != d.span.file)
+    {
+        writeln!(
+            out,
+            "     {} {}: {} at {}",
+            style.paint(BLUE, "="),
+            style.paint(BOLD, &Level::Note.to_string()),
+            label.msg,
+            location(sources, label.span)
+        )?;
+    }
+
+    for &(level, ref msg) in &d.children {
+        writeln!(
+            out,
+            "     {} {}: {}",
+            style.paint(BLUE, "="),
+            style.paint(BOLD, &level.to_string()),
+            msg
+        )?;
+    }
+
+    for suggestion in &d.suggestions {
+        writeln!(
+            out,
+            "{}{}",
+            style.paint(Level::Help.color(), &Level::Help.to_string()),
+            style.paint(BOLD, &format!(": {}", suggestion.msg))
+        )?;
+
+        let span = suggestion.span;
+        let input = sources.source(span.file);
+        let line_start = input[..span.start.absolute]
+            .rfind('\n')
+            .map_or(0, |idx| idx + 1);
+        let line_end = input[span.end.absolute..]
+            .find('\n')
+            .map_or(input.len(), |idx| span.end.absolute + idx);
+
+        writeln!(
+            out,
+            "{} {} {}{}{}",
+            style.paint(BLUE, &format!("{:>4}", span.start.line)),
+            gutter,
+            &input[line_start..span.start.absolute],
+            style.paint(GREEN, &suggestion.replacement),
+            &input[span.end.absolute..line_end]
+        )?;
+    }
+
+    Ok(())
+}
+
+fn location(sources: &SourceMap, span: Span) -> String {
+    format!(
+        "{}:{}:{}",
+        sources.name(span.file),
+        span.start.line,
+        span.start.column
+    )
+}
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const GREEN: &str = "\x1b[1;32m";
+const CYAN: &str = "\x1b[1;36m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Wraps text in ANSI escape codes when colors are enabled
+struct Style(bool);
+
+impl Style {
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.0 {
+            format!("{}{}{}", code, text, RESET)
+        } else {
+            text.into()
         }
     }
 }
 
-pub fn print(input: &str, d: &Diagnostic) {
-    let prefix = "| ";
+impl Level {
+    fn color(self) -> &'static str {
+        match self {
+            Level::Error => RED,
+            Level::Warning => YELLOW,
+            Level::Note => BOLD,
+            Level::Help => CYAN,
+        }
+    }
+}
 
-    println!("{}: {}", d.level, d.msg);
+/// The markers under `line` for the part of `span` on it. Spans end after their last char,
+/// so an empty span is marked with a single char
+fn underline(span: Span, line_idx: u32, line: &str, mark: char) -> Option<String> {
+    if line_idx < span.start.line || line_idx > span.end.line {
+        return None;
+    }
 
-    let span = d.span;
+    let len = line.chars().count();
 
-    let start_line = if span.start.line >= 4 {
-        span.start.line - 4
+    let start = if line_idx == span.start.line {
+        span.start.column.saturating_sub(1) as usize
     } else {
         0
     };
 
-    for (idx, line) in input.lines().enumerate().skip(start_line as usize) {
-        let line = line;
-        let line_idx = idx + 1;
-        println!("{:>4} {}{}", line_idx, prefix, line);
-        if line_idx == span.start.line as usize {
-            let end = if line_idx == span.end.line as usize {
-                span.end.column as usize
-            } else {
-                line.len()
-            };
-            let carets = repeat_string("^", end - span.start.column as usize + 1);
-
-            let carets = match d.level {
-                Level::Error => carets,
-            };
-
-            let whitespace = repeat_string(" ", span.start.column as usize);
-            println!("     {}{}{}", prefix, whitespace, carets);
-        } else if line_idx == span.end.line as usize {
-            let carets = repeat_string("^", span.end.column as usize);
-            let carets = match d.level {
-                Level::Error => carets,
-            };
-            println!("     {}{}", prefix, carets);
-        } else if line_idx > span.start.line as usize
-            && line_idx < span.end.line as usize
-            && !line.is_empty()
-        {
-            let carets = repeat_string("^", line.len());
-            let carets = match d.level {
-                Level::Error => carets,
-            };
-            println!("     {}{}", prefix, carets);
-        }
-
-        if line_idx >= span.end.line as usize + 3 {
-            break;
-        }
+    let end = if line_idx == span.end.line {
+        span.end.column.saturating_sub(1) as usize
+    } else {
+        len
+    };
+
+    if end <= start && line_idx != span.start.line {
+        return None;
     }
+
+    let marks = repeat_string(&mark.to_string(), end.saturating_sub(start).max(1));
+
+    Some(format!("{}{}", repeat_string(" ", start), marks))
 }
 
 fn repeat_string(s: &str, count: usize) -> String {
-    ::std::iter::repeat(s).take(count).collect()
+    s.repeat(count)
 }
@@ -0,0 +1,53 @@
+//! An embeddable Lox interpreter.
+//!
+//! ```
+//! extern crate lox;
+//!
+//! let mut vm = lox::VM::new();
+//! vm.set_global("answer", lox::Value::number(42.0));
+//!
+//! let value = vm.eval("answer + 1;").unwrap();
+//! assert_eq!(value.try_number(), Some(43.0));
+//! ```
+
+// Errors are reported through the `Reporter` so parsing and lexing only signal failure,
+// and object constructors hand back the raw pointer that gets linked into the object list
+#![allow(
+    clippy::result_unit_err,
+    clippy::should_implement_trait,
+    clippy::new_ret_no_self
+)]
+
+extern crate libc;
+
+#[macro_use]
+mod macros;
+pub mod bytecode;
+pub mod chunks;
+pub mod codes;
+mod compiler;
+pub mod error;
+mod lists;
+mod maps;
+mod math;
+mod native;
+mod object;
+pub mod op;
+pub mod pos;
+pub mod scanner;
+mod strings;
+mod table;
+pub mod token;
+pub mod trace;
+mod value;
+pub mod verify;
+mod vm;
+
+use op::opcode;
+
+pub use error::{Error, RuntimeError};
+pub use value::{HostValue, Value, ValueType};
+pub use vm::VM;
+
+/// The interpreter is the vm; this alias exists for embedders who prefer the language name.
+pub type Lox = VM;
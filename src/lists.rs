@@ -0,0 +1,67 @@
+use error::RuntimeError;
+use object::ObjectType;
+use value::Value;
+use vm::VM;
+
+/// Defines the methods callable on lists. Every method receives the list as `args[0]`
+pub fn define_methods(vm: &mut VM) {
+    vm.define_method(ObjectType::List, "len", 0, |_, args| {
+        Ok(Value::number(args[0].as_list().items.len() as f32))
+    });
+
+    vm.define_method(ObjectType::List, "push", 1, |_, args| {
+        args[0].as_list_mut().items.push(args[1]);
+        Ok(Value::nil())
+    });
+
+    vm.define_method(ObjectType::List, "pop", 0, |_, args| {
+        match args[0].as_list_mut().items.pop() {
+            Some(value) => Ok(value),
+            None => Err(RuntimeError::new("Cannot pop from an empty list.")),
+        }
+    });
+
+    vm.define_method(ObjectType::List, "insert", 2, |_, args| {
+        let items = &mut args[0].as_list_mut().items;
+        // Inserting at the length appends
+        let index = position("insert", args[1], items.len() + 1)?;
+
+        items.insert(index, args[2]);
+        Ok(Value::nil())
+    });
+
+    vm.define_method(ObjectType::List, "remove", 1, |_, args| {
+        let items = &mut args[0].as_list_mut().items;
+        let index = position("remove", args[1], items.len())?;
+
+        Ok(items.remove(index))
+    });
+}
+
+/// Converts an index argument into a position below `len`, with negative indices counting back from `len`
+fn position(name: &str, index: Value, len: usize) -> Result<usize, RuntimeError> {
+    let number = match index.try_number() {
+        Some(number) if number.fract() == 0.0 => number,
+        _ => {
+            return Err(RuntimeError::new(format!(
+                "`{}` expected a whole number index but got `{}`.",
+                name, index
+            )))
+        }
+    };
+
+    let position = if number < 0.0 {
+        len as f32 + number
+    } else {
+        number
+    };
+
+    if position < 0.0 || position >= len as f32 {
+        return Err(RuntimeError::new(format!(
+            "`{}` index {} is out of bounds.",
+            name, number
+        )));
+    }
+
+    Ok(position as usize)
+}
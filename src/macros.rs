@@ -1,8 +1,3 @@
-#[macro_export]
-macro_rules! debug {
-        ($($p:tt)*) => {if cfg!(feature = "debug") { println!($($p)*) } else { }}
-}
-
 macro_rules! binary_op {
     ($op:tt,$return_ty:ident,$_self:ident) => {{
 
@@ -21,9 +16,11 @@ macro_rules! binary_op {
 
 macro_rules! eof_error {
     ($_self:ident) => {{
-        let msg = format!("Unexpected EOF");
         let end = $_self.reporter.end();
-        $_self.reporter.error(msg, end);
+        $_self.reporter.report(
+            ::error::Diagnostic::new(::error::Level::Error, "Unexpected EOF", end)
+                .code(::codes::UNEXPECTED_EOF),
+        );
         Err(())
     }};
 }
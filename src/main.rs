@@ -1,129 +1,186 @@
-#![feature(nll)]
-
 use std::alloc::System;
 
 #[global_allocator]
 static A: System = System;
 
-extern crate libc;
-
-#[macro_use]
-mod value;
-#[macro_use]
-mod macros;
-mod chunks;
-mod compiler;
-mod error;
-mod object;
-mod op;
-mod pos;
-mod scanner;
-mod token;
-mod vm;
-
-use compiler::Compiler;
-use error::Reporter;
-
-use op::opcode;
-use scanner::Lexer;
+extern crate lox;
+
+use lox::bytecode;
+use lox::codes;
+use lox::error::{ColorChoice, Reporter};
+use lox::pos::SourceMap;
+use lox::trace::Tracer;
+use lox::{Error, VM};
 use std::env;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, Read, Write};
-use vm::VM;
+use std::path::Path;
+
+const USAGE: &str =
+    "Usage: rlox [--deny-warnings] [--color=always|never|auto] [--error-format=human|json]
+            [--module-path=<dir>]... [--trace[=<name>]] [path]
+       rlox --compile <path>
+       rlox --disassemble <path>
+       rlox --explain <code>";
+
+/// How compile diagnostics are printed
+struct Output {
+    color: ColorChoice,
+    json: bool,
+}
+
+impl Output {
+    fn emit(&self, reporter: &Reporter, sources: &SourceMap) {
+        if self.json {
+            reporter.emit_json(sources)
+        } else {
+            reporter.emit(sources, self.color)
+        }
+    }
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut vm = VM::new();
+    let mut output = Output {
+        color: ColorChoice::Auto,
+        json: false,
+    };
+    let mut paths = vec![];
+    let mut compile = false;
+    let mut disassemble = false;
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--explain" => match args.next() {
+                Some(code) => explain(&code),
+                None => usage_error("`--explain` expects an error code such as E0001"),
+            },
+            "--deny-warnings" => vm.deny_warnings(true),
+            "--compile" => compile = true,
+            "--trace" => vm.trace(Some(Tracer::new(io::stderr()))),
+            flag if flag.starts_with("--trace=") => vm.trace(Some(
+                Tracer::new(io::stderr()).only(&flag["--trace=".len()..]),
+            )),
+            "--disassemble" => disassemble = true,
+            "--error-format=human" => output.json = false,
+            "--error-format=json" => output.json = true,
+            flag if flag.starts_with("--color=") => match flag["--color=".len()..].parse() {
+                Ok(choice) => output.color = choice,
+                Err(e) => usage_error(&e),
+            },
+            flag if flag.starts_with("--module-path=") => {
+                vm.add_search_path(&flag["--module-path=".len()..])
+            }
+            flag if flag.starts_with("--") => usage_error(&format!("Unknown option `{}`", flag)),
+            _ => paths.push(arg),
+        }
+    }
 
-    match args.len() {
-        1 => repl(),
-        2 => run_file(&args[1]),
+    match paths.len() {
+        1 if compile => compile_file(vm, &output, &paths[0]),
+        1 if disassemble => disassemble_file(vm, &output, &paths[0]),
+        0 if !compile && !disassemble => repl(vm, &output),
+        1 => run_file(vm, &output, &paths[0]),
+
+        _ => println!("{}", USAGE),
+    }
+}
 
-        _ => println!("Usage: rlox [path]"),
+fn explain(code: &str) -> ! {
+    match codes::explain(code) {
+        Some(explanation) => {
+            println!("{}", explanation);
+            ::std::process::exit(0)
+        }
+        None => {
+            eprintln!("`{}` is not a known error code", code);
+            ::std::process::exit(1)
+        }
     }
 }
 
-fn repl() {
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{}\n{}", msg, USAGE);
+    ::std::process::exit(64)
+}
+
+fn repl(mut vm: VM, output: &Output) {
     loop {
         let _ = io::stdout().write(b"lexer>> ");
         let _ = io::stdout().flush();
         let mut input = String::new();
 
-        io::stdin()
+        let read = io::stdin()
             .read_line(&mut input)
             .expect("Couldn't read the input");
 
+        if read == 0 {
+            break;
+        }
+
         let input = input.trim();
 
         if input.is_empty() {
             continue;
         }
 
-        let reporter = Reporter::new();
-
-        let mut lex = Lexer::new(&input, reporter.clone());
-
-        let tokens = match lex.lex() {
-            Ok(tokens) => tokens,
-            Err(_) => {
-                reporter.emit(&input);
-                continue;
+        match vm.eval_file("<repl>", input) {
+            Ok(value) => {
+                output.emit(vm.diagnostics(), vm.source_map());
+                println!("{}", value)
             }
-        };
-
-        let mut compiler = Compiler::new(reporter.clone(), tokens);
-
-        if let Err(_) = compiler.compile() {
-            reporter.emit(&input);
-            continue;
+            Err(Error::Compile(reporter)) => output.emit(&reporter, vm.source_map()),
+            Err(e) => eprintln!("{}", e),
         }
-
-        let mut vm = VM::new(&compiler.chunks[0], compiler.objects);
-
-        vm.interpret();
     }
 }
 
-/*
-fn test_file() {
-    let mut file =
-        File::open("/Users/rowlandsonpratt/Lenard/Rust/lox/src/test.tox").expect("File not found");
+fn run_file(mut vm: VM, output: &Output, path: &str) {
+    let mut file = File::open(path).expect("File not found");
 
-    let mut contents = String::new();
+    let mut contents = vec![];
 
-    file.read_to_string(&mut contents)
+    file.read_to_end(&mut contents)
         .expect("something went wrong reading the file");
 
-    let input = contents.trim();
-
-    if contents.is_empty() {
-        ::std::process::exit(0)
-    }
+    let result = if bytecode::is_bytecode(&contents) {
+        vm.eval_bytecode(path, &contents)
+    } else {
+        let contents = String::from_utf8(contents).expect("The file is not valid utf-8");
 
-    let reporter = Reporter::new();
-
-    let mut lex = Lexer::new(&input, reporter.clone());
-
-    let tokens = match lex.lex() {
-        Ok(tokens) => tokens,
-        Err(_) => {
-            reporter.emit(&input);
-            ::std::process::exit(64)
+        if contents.trim().is_empty() {
+            ::std::process::exit(0)
         }
+
+        vm.eval_file(path, &contents)
     };
 
-    let mut compiler = Compiler::new(reporter.clone(), tokens);
+    match result {
+        Ok(value) => {
+            output.emit(vm.diagnostics(), vm.source_map());
 
-    if let Err(_) = compiler.compile() {
-        reporter.emit(&input);
-        println!("{:#?}", compiler);
+            if !value.is_nil() {
+                println!("{}", value)
+            }
+        }
+        Err(Error::Compile(reporter)) => {
+            output.emit(&reporter, vm.source_map());
+            ::std::process::exit(65)
+        }
+        Err(Error::Runtime(e)) => {
+            eprintln!("{}", e);
+            ::std::process::exit(70)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ::std::process::exit(65)
+        }
     }
+}
 
-    let mut vm = VM::new(&compiler.chunks[0],compiler.objects);
-
-    vm.interpret();
-}*/
-
-fn run_file(path: &str) {
+/// Writes the compiled file next to the source with a `.loxc` extension
+fn compile_file(mut vm: VM, output: &Output, path: &str) {
     let mut file = File::open(path).expect("File not found");
 
     let mut contents = String::new();
@@ -131,31 +188,54 @@ fn run_file(path: &str) {
     file.read_to_string(&mut contents)
         .expect("something went wrong reading the file");
 
-    let input = contents.trim();
+    match vm.compile_bytecode(path, &contents) {
+        Ok(bytes) => {
+            output.emit(vm.diagnostics(), vm.source_map());
+
+            let out = Path::new(path).with_extension("loxc");
 
-    if contents.is_empty() {
-        ::std::process::exit(0)
+            if let Err(e) = fs::write(&out, bytes) {
+                eprintln!("error: Couldn't write `{}`: {}", out.display(), e);
+                ::std::process::exit(74)
+            }
+        }
+        Err(Error::Compile(reporter)) => {
+            output.emit(&reporter, vm.source_map());
+            ::std::process::exit(65)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ::std::process::exit(65)
+        }
     }
+}
 
-    let reporter = Reporter::new();
+/// Prints the instructions of a source or compiled file
+fn disassemble_file(mut vm: VM, output: &Output, path: &str) {
+    let contents = fs::read(path).expect("File not found");
 
-    let mut lex = Lexer::new(&input, reporter.clone());
+    let result = if bytecode::is_bytecode(&contents) {
+        vm.disassemble_bytecode(path, &contents)
+    } else {
+        let contents = String::from_utf8(contents).expect("The file is not valid utf-8");
 
-    let tokens = match lex.lex() {
-        Ok(tokens) => tokens,
-        Err(_) => {
-            reporter.emit(&input);
-            ::std::process::exit(64)
-        }
+        vm.disassemble(path, &contents)
     };
 
-    let mut compiler = Compiler::new(reporter.clone(), tokens);
+    match result {
+        Ok(listing) => {
+            output.emit(vm.diagnostics(), vm.source_map());
 
-    if let Err(_) = compiler.compile() {
-        reporter.emit(&input);
+            let stdout = io::stdout();
+            let _ = write!(stdout.lock(), "{}", listing);
+        }
+        Err(Error::Compile(reporter)) => {
+            output.emit(&reporter, vm.source_map());
+            ::std::process::exit(65)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ::std::process::exit(65)
+        }
     }
-
-    let mut vm = VM::new(&compiler.chunks[0], compiler.objects);
-
-    vm.interpret();
 }
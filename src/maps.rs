@@ -0,0 +1,55 @@
+use error::RuntimeError;
+use object::ObjectType;
+use table::Table;
+use value::Value;
+use vm::VM;
+
+/// Defines the methods callable on maps. Every method receives the map as `args[0]`
+pub fn define_methods(vm: &mut VM) {
+    vm.define_method(ObjectType::Map, "len", 0, |_, args| {
+        Ok(Value::number(args[0].as_map().table.len() as f32))
+    });
+
+    vm.define_method(ObjectType::Map, "keys", 0, |vm, args| {
+        let keys = args[0].as_map().table.iter().map(|(key, _)| key).collect();
+        Ok(vm.list(keys))
+    });
+
+    vm.define_method(ObjectType::Map, "values", 0, |vm, args| {
+        let values = args[0]
+            .as_map()
+            .table
+            .iter()
+            .map(|(_, value)| value)
+            .collect();
+        Ok(vm.list(values))
+    });
+
+    vm.define_method(ObjectType::Map, "has", 1, |_, args| {
+        key("has", args[1])?;
+        Ok(Value::bool(args[0].as_map().table.get(&args[1]).is_some()))
+    });
+
+    vm.define_method(ObjectType::Map, "remove", 1, |_, args| {
+        key("remove", args[1])?;
+
+        match args[0].as_map_mut().table.remove(&args[1]) {
+            Some(value) => Ok(value),
+            None => Err(RuntimeError::new(format!(
+                "Key `{}` is not in the map.",
+                args[1]
+            ))),
+        }
+    });
+}
+
+fn key(name: &str, key: Value) -> Result<(), RuntimeError> {
+    if Table::is_key(&key) {
+        Ok(())
+    } else {
+        Err(RuntimeError::new(format!(
+            "`{}` expected a string, number, bool or nil key but got `{}`.",
+            name, key
+        )))
+    }
+}
@@ -0,0 +1,117 @@
+use error::RuntimeError;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::f32::consts;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use value::Value;
+use vm::VM;
+
+/// Defines the `Math` module
+pub fn define_math(vm: &mut VM) {
+    let mut fields = HashMap::new();
+
+    fields.insert("PI".into(), Value::number(consts::PI));
+    fields.insert("E".into(), Value::number(consts::E));
+
+    unary(vm, &mut fields, "sqrt", f32::sqrt);
+    unary(vm, &mut fields, "floor", f32::floor);
+    unary(vm, &mut fields, "ceil", f32::ceil);
+    unary(vm, &mut fields, "round", f32::round);
+    unary(vm, &mut fields, "abs", f32::abs);
+    unary(vm, &mut fields, "sin", f32::sin);
+    unary(vm, &mut fields, "cos", f32::cos);
+    unary(vm, &mut fields, "tan", f32::tan);
+    unary(vm, &mut fields, "asin", f32::asin);
+    unary(vm, &mut fields, "acos", f32::acos);
+    unary(vm, &mut fields, "atan", f32::atan);
+    unary(vm, &mut fields, "log", f32::ln);
+    unary(vm, &mut fields, "exp", f32::exp);
+
+    binary(vm, &mut fields, "pow", f32::powf);
+    binary(vm, &mut fields, "atan2", f32::atan2);
+    binary(vm, &mut fields, "min", f32::min);
+    binary(vm, &mut fields, "max", f32::max);
+
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_nanos() as u64)
+        .unwrap_or(0);
+
+    let state = Rc::new(Cell::new(scramble(seed)));
+
+    let random = {
+        let state = state.clone();
+
+        vm.native("random", 0, move |_, _| {
+            Ok(Value::number(next_random(&state)))
+        })
+    };
+
+    let seed = vm.native("seed", 1, move |_, args| {
+        let seed = number("seed", args, 0)?;
+        state.set(scramble(seed.to_bits() as u64));
+        Ok(Value::nil())
+    });
+
+    fields.insert("random".into(), random);
+    fields.insert("seed".into(), seed);
+
+    vm.define_module("Math", fields);
+}
+
+fn unary(vm: &mut VM, fields: &mut HashMap<String, Value>, name: &'static str, f: fn(f32) -> f32) {
+    let native = vm.native(name, 1, move |_, args| {
+        Ok(Value::number(f(number(name, args, 0)?)))
+    });
+
+    fields.insert(name.into(), native);
+}
+
+fn binary(
+    vm: &mut VM,
+    fields: &mut HashMap<String, Value>,
+    name: &'static str,
+    f: fn(f32, f32) -> f32,
+) {
+    let native = vm.native(name, 2, move |_, args| {
+        Ok(Value::number(f(
+            number(name, args, 0)?,
+            number(name, args, 1)?,
+        )))
+    });
+
+    fields.insert(name.into(), native);
+}
+
+/// Returns the argument at `index` as a number, raising a runtime error if it's not one
+fn number(name: &str, args: &[Value], index: usize) -> Result<f32, RuntimeError> {
+    if args[index].is_number() {
+        Ok(args[index].as_number())
+    } else {
+        Err(RuntimeError::new(format!(
+            "`Math.{}` expected a number but got `{}`.",
+            name, args[index]
+        )))
+    }
+}
+
+/// The xorshift state can never be zero, so every seed is mixed with a constant first
+fn scramble(seed: u64) -> u64 {
+    (seed ^ 0x9E37_79B9_7F4A_7C15) | 1
+}
+
+/// Returns a number in `[0, 1)` using xorshift64*
+fn next_random(state: &Cell<u64>) -> f32 {
+    let mut x = state.get();
+
+    x ^= x >> 12;
+    x ^= x << 25;
+    x ^= x >> 27;
+
+    state.set(x);
+
+    let bits = x.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 40; // the top 24 bits fit an f32 exactly
+
+    bits as f32 / (1u64 << 24) as f32
+}
@@ -0,0 +1,82 @@
+use error::RuntimeError;
+use std::time::Instant;
+use value::Value;
+use vm::VM;
+
+/// Registers the functions every vm starts with
+pub fn define_core(vm: &mut VM) {
+    let start = Instant::now();
+
+    vm.define_native("clock", 0, move |_, _| {
+        Ok(Value::number(start.elapsed().as_secs_f32()))
+    });
+    vm.define_native("type", 1, type_of);
+    vm.define_native("str", 1, str);
+    vm.define_native("num", 1, num);
+    vm.define_native("len", 1, len);
+    vm.define_native("assert", 2, assert);
+}
+
+fn type_of(vm: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    let name = if args[0].is_object() {
+        args[0].obj_ty().to_string()
+    } else {
+        args[0].ty().to_string()
+    };
+
+    Ok(vm.string(&name))
+}
+
+fn str(vm: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    if args[0].is_string() {
+        return Ok(args[0]);
+    }
+
+    Ok(vm.string(&args[0].to_string()))
+}
+
+fn num(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    if args[0].is_number() {
+        return Ok(args[0]);
+    }
+
+    let string = match args[0].try_str() {
+        Some(string) => string.trim(),
+        None => {
+            let msg = format!("`num` expected a string but got `{}`.", args[0]);
+            return Err(RuntimeError::new(msg));
+        }
+    };
+
+    // Like number literals, infinities and NaN aren't numbers lox can write
+    match string.parse::<f32>() {
+        Ok(number) if number.is_finite() => Ok(Value::number(number)),
+        _ => Err(RuntimeError::new(format!(
+            "Could not convert \"{}\" to a number.",
+            string
+        ))),
+    }
+}
+
+fn len(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    if args[0].is_list() {
+        return Ok(Value::number(args[0].as_list().items.len() as f32));
+    }
+
+    if args[0].is_map() {
+        return Ok(Value::number(args[0].as_map().table.len() as f32));
+    }
+
+    match args[0].try_str() {
+        Some(string) => Ok(Value::number(string.chars().count() as f32)),
+        None => Err(RuntimeError::new(format!("`{}` has no length.", args[0]))),
+    }
+}
+
+fn assert(_: &mut VM, args: &[Value]) -> Result<Value, RuntimeError> {
+    if args[0].is_falsey() {
+        Err(RuntimeError::new(format!("Assertion failed: {}", args[1])))
+    } else {
+        Ok(Value::nil())
+    }
+}
@@ -1,13 +1,26 @@
-use std::fmt::{self, Display};
-use std::ops::Deref;
+use error::RuntimeError;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display};
 use std::mem;
+use std::ops::Deref;
+use table::Table;
+use value::Value;
+use vm::VM;
 
 pub type RawObject = *mut Object;
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+/// A function implemented in rust that can be called from lox
+pub type NativeFn = Box<dyn Fn(&mut VM, &[Value]) -> Result<Value, RuntimeError>>;
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 #[repr(C)]
 pub enum ObjectType {
     String,
+    Native,
+    Module,
+    List,
+    Map,
 }
 
 #[derive(Debug, Clone)]
@@ -15,6 +28,8 @@ pub enum ObjectType {
 pub struct Object {
     pub ty: ObjectType,
     pub next: RawObject,
+    /// Set while the garbage collector is tracing reachable objects
+    pub marked: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -24,6 +39,37 @@ pub struct StringObject<'a> {
     pub chars: ObjectValue<'a>,
 }
 
+#[repr(C)]
+pub struct NativeObject {
+    pub obj: Object,
+    pub name: String,
+    pub arity: usize,
+    pub function: NativeFn,
+}
+
+/// A namespace of values accessed with `.`
+#[derive(Debug)]
+#[repr(C)]
+pub struct ModuleObject {
+    pub obj: Object,
+    pub name: String,
+    pub fields: HashMap<String, Value>,
+}
+
+#[derive(Debug)]
+#[repr(C)]
+pub struct ListObject {
+    pub obj: Object,
+    pub items: Vec<Value>,
+}
+
+#[derive(Debug)]
+#[repr(C)]
+pub struct MapObject {
+    pub obj: Object,
+    pub table: Table,
+}
+
 #[derive(Debug, Clone)]
 #[repr(C)]
 pub enum ObjectValue<'a> {
@@ -33,7 +79,27 @@ pub enum ObjectValue<'a> {
 
 impl Object {
     pub fn new(ty: ObjectType, next: RawObject) -> Self {
-        Object { ty, next }
+        Object {
+            ty,
+            next,
+            marked: false,
+        }
+    }
+
+    /// Frees an object allocated by one of the object constructors,
+    /// returning the next object in the list
+    pub unsafe fn free(object: RawObject) -> RawObject {
+        let next = (*object).next;
+
+        match (*object).ty {
+            ObjectType::String => mem::drop(Box::from_raw(object as *mut StringObject)),
+            ObjectType::Native => mem::drop(Box::from_raw(object as *mut NativeObject)),
+            ObjectType::Module => mem::drop(Box::from_raw(object as *mut ModuleObject)),
+            ObjectType::List => mem::drop(Box::from_raw(object as *mut ListObject)),
+            ObjectType::Map => mem::drop(Box::from_raw(object as *mut MapObject)),
+        }
+
+        next
     }
 }
 
@@ -60,10 +126,57 @@ impl<'a> StringObject<'a> {
     }
 }
 
+impl NativeObject {
+    pub fn new(name: &str, arity: usize, function: NativeFn, next: RawObject) -> RawObject {
+        let native = NativeObject {
+            obj: Object::new(ObjectType::Native, next),
+            name: name.into(),
+            arity,
+            function,
+        };
+
+        Box::into_raw(Box::new(native)) as RawObject
+    }
+}
+
+impl ModuleObject {
+    pub fn new(name: &str, fields: HashMap<String, Value>, next: RawObject) -> RawObject {
+        let module = ModuleObject {
+            obj: Object::new(ObjectType::Module, next),
+            name: name.into(),
+            fields,
+        };
+
+        Box::into_raw(Box::new(module)) as RawObject
+    }
+}
+
+impl ListObject {
+    pub fn new(items: Vec<Value>, next: RawObject) -> RawObject {
+        let list = ListObject {
+            obj: Object::new(ObjectType::List, next),
+            items,
+        };
+
+        Box::into_raw(Box::new(list)) as RawObject
+    }
+}
+
+impl MapObject {
+    pub fn new(table: Table, next: RawObject) -> RawObject {
+        let map = MapObject {
+            obj: Object::new(ObjectType::Map, next),
+            table,
+        };
+
+        Box::into_raw(Box::new(map)) as RawObject
+    }
+}
+
 impl<'a> ObjectValue<'a> {
     pub fn string(&self) -> &str {
         match *self {
-            ObjectValue::Str(ref string) => string,
+            ObjectValue::Str(string) => string,
             ObjectValue::String(ref string) => string,
         }
     }
@@ -77,44 +190,138 @@ impl<'a> Deref for StringObject<'a> {
     }
 }
 
-impl Drop for Object {
-    fn drop(&mut self) {
-
-        match self.ty {
-            ObjectType::String => {
-                unsafe {
-                    let string:&StringObject = mem::transmute(self);
-
-                    mem::drop(string);
-                }
-            }
+impl<'a> Display for ObjectValue<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ObjectValue::Str(ref string) => write!(f, "{}", string)?,
+            ObjectValue::String(ref string) => write!(f, "{}", string)?,
         }
+        Ok(())
     }
 }
 
-impl <'a> Drop for StringObject<'a> {
-    fn drop(&mut self) {
-        match &self.chars {
-            ObjectValue::String(string) => mem::drop(string),
-            _ => ()
-        }
+impl<'a> Display for StringObject<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.chars)?;
+
+        Ok(())
     }
 }
 
-impl<'a> Display for ObjectValue<'a> {
+impl Display for ObjectType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            ObjectValue::Str(ref string) => write!(f, "static: {}", string)?,
-            ObjectValue::String(ref string) => write!(f, "new: {}", string)?,
+            ObjectType::String => write!(f, "string"),
+            ObjectType::Native => write!(f, "native"),
+            ObjectType::Module => write!(f, "module"),
+            ObjectType::List => write!(f, "list"),
+            ObjectType::Map => write!(f, "map"),
         }
-        Ok(())
     }
 }
 
-impl<'a> Display for StringObject<'a> {
+impl Debug for NativeObject {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.chars)?;
+        write!(
+            f,
+            "NativeObject {{ name: {:?}, arity: {} }}",
+            self.name, self.arity
+        )
+    }
+}
 
-        Ok(())
+impl Display for NativeObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl Display for ModuleObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
+
+impl Display for ListObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        display_once(f, &self.obj, "[...]", |f| {
+            write!(f, "[")?;
+
+            for (i, item) in self.items.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+
+                write_item(f, item)?;
+            }
+
+            write!(f, "]")
+        })
+    }
+}
+
+impl Display for MapObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        display_once(f, &self.obj, "{...}", |f| {
+            write!(f, "{{")?;
+
+            for (i, (key, value)) in self.table.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+
+                write_item(f, &key)?;
+                write!(f, ": ")?;
+                write_item(f, &value)?;
+            }
+
+            write!(f, "}}")
+        })
+    }
+}
+
+thread_local! {
+    /// The lists and maps being displayed, outermost first
+    static DISPLAYING: RefCell<Vec<*const Object>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Displays a list or map with `write`, showing `placeholder` instead if it is inside itself
+fn display_once<F>(
+    f: &mut fmt::Formatter,
+    object: &Object,
+    placeholder: &str,
+    write: F,
+) -> fmt::Result
+where
+    F: FnOnce(&mut fmt::Formatter) -> fmt::Result,
+{
+    let object = object as *const Object;
+    let cycle = DISPLAYING.with(|displaying| {
+        let mut displaying = displaying.borrow_mut();
+        let cycle = displaying.contains(&object);
+
+        if !cycle {
+            displaying.push(object);
+        }
+
+        cycle
+    });
+
+    if cycle {
+        return write!(f, "{}", placeholder);
+    }
+
+    let result = write(f);
+
+    DISPLAYING.with(|displaying| displaying.borrow_mut().pop());
+
+    result
+}
+
+/// Strings inside collections are quoted so `["1"]` and `[1]` display differently
+fn write_item(f: &mut fmt::Formatter, item: &Value) -> fmt::Result {
+    match item.try_str() {
+        Some(string) => write!(f, "{:?}", string),
+        None => write!(f, "{}", item),
     }
 }
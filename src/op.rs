@@ -1,17 +1,122 @@
-pub mod opcode {
-    pub const RETURN: u8 = 0x0;
-    pub const CONSTANT: u8 = 0x1;
-    pub const NEGATE: u8 = 0x2;
-    pub const ADD: u8 = 0x3;
-    pub const SUB: u8 = 0x4;
-    pub const MUL: u8 = 0x5;
-    pub const DIV: u8 = 0x6;
-    pub const NIL: u8 = 0x7;
-    pub const TRUE: u8 = 0x8;
-    pub const FALSE: u8 = 0x9;
-    pub const NOT: u8 = 0x10;
-    pub const EQUAL: u8 = 0x11;
-    pub const GREATER: u8 = 0x12;
-    pub const LESS: u8 = 0x13;
-    pub const INDEX:u8 = 0x14;
+//! The instruction set, defined once in the table below. The opcode constants emitted by the
+//! compiler, the `OpCode` enum matched by the vm, and the names, operands and stack effects
+//! used by the disassembler and verifier are all generated from it, so an opcode can't be
+//! added without all of them.
+
+use std::convert::TryFrom;
+
+/// How an operand byte is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    /// An index into the constants
+    Constant,
+    /// An index of a string constant naming a global, property or module
+    Name,
+    /// A count of values on the stack
+    Count,
+}
+
+/// Generates the instruction set from rows of
+/// `byte Variant CONSTANT [operands] pops => pushes`, where `pops` may add a multiple of a
+/// count operand with `+ scale * operand index`
+macro_rules! opcodes {
+    ($(
+        $(#[$doc:meta])*
+        $byte:literal $variant:ident $constant:ident [$($operand:ident),*]
+            $pops:literal $(+ $scale:literal * operand $index:literal)? => $pushes:literal,
+    )*) => {
+        /// The opcodes as bytes, for emitting code
+        pub mod opcode {
+            $(pub const $constant: u8 = $byte;)*
+        }
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum OpCode {
+            $(
+                $(#[$doc])*
+                $variant = $byte,
+            )*
+        }
+
+        impl OpCode {
+            /// Every opcode in the order of its byte
+            pub const ALL: &'static [OpCode] = &[$(OpCode::$variant),*];
+
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(OpCode::$variant => stringify!($constant),)*
+                }
+            }
+
+            /// The operand bytes following the opcode
+            pub fn operands(self) -> &'static [Operand] {
+                match self {
+                    $(OpCode::$variant => &[$(Operand::$operand),*],)*
+                }
+            }
+
+            /// The number of values the instruction pops and then pushes, given its operands
+            #[allow(clippy::identity_op)]
+            pub fn stack_effect(self, operands: &[u8]) -> (usize, usize) {
+                match self {
+                    $(
+                        OpCode::$variant => (
+                            $pops $(+ $scale * operands[$index] as usize)?,
+                            $pushes,
+                        ),
+                    )*
+                }
+            }
+        }
+
+        impl TryFrom<u8> for OpCode {
+            /// The byte, which isn't an opcode
+            type Error = u8;
+
+            fn try_from(byte: u8) -> Result<OpCode, u8> {
+                match byte {
+                    $($byte => Ok(OpCode::$variant),)*
+                    _ => Err(byte),
+                }
+            }
+        }
+    };
+}
+
+opcodes! {
+    /// Returns the value on top of the stack as the result of the chunk
+    0x00 Return RETURN [] 1 => 0,
+    0x01 Constant CONSTANT [Constant] 0 => 1,
+    0x02 Negate NEGATE [] 1 => 1,
+    /// Adds numbers or concatenates strings
+    0x03 Add ADD [] 2 => 1,
+    0x04 Sub SUB [] 2 => 1,
+    0x05 Mul MUL [] 2 => 1,
+    0x06 Div DIV [] 2 => 1,
+    0x07 Nil NIL [] 0 => 1,
+    0x08 True TRUE [] 0 => 1,
+    0x09 False FALSE [] 0 => 1,
+    0x0a Not NOT [] 1 => 1,
+    0x0b Equal EQUAL [] 2 => 1,
+    0x0c Greater GREATER [] 2 => 1,
+    0x0d Less LESS [] 2 => 1,
+    /// Indexes the receiver below the index
+    0x0e IndexGet INDEX_GET [] 2 => 1,
+    0x0f Pop POP [] 1 => 0,
+    0x10 DefineGlobal DEFINE_GLOBAL [Name] 1 => 0,
+    0x11 GetGlobal GET_GLOBAL [Name] 0 => 1,
+    /// Calls the callee below the arguments
+    0x12 Call CALL [Count] 1 + 1 * operand 0 => 1,
+    0x13 GetProperty GET_PROPERTY [Name] 1 => 1,
+    /// Calls a method or module function on the receiver below the arguments
+    0x14 Invoke INVOKE [Name, Count] 1 + 1 * operand 1 => 1,
+    /// Assigns the value on top to an index of the receiver, leaving the value
+    0x15 IndexSet INDEX_SET [] 3 => 1,
+    0x16 BuildList BUILD_LIST [Count] 0 + 1 * operand 0 => 1,
+    /// Builds a map from pairs of keys and values
+    0x17 BuildMap BUILD_MAP [Count] 0 + 2 * operand 0 => 1,
+    /// Converts a value that isn't a string to its string
+    0x18 ToString TO_STRING [] 1 => 1,
+    /// Pushes the module imported from the path
+    0x19 Import IMPORT [Name] 0 => 1,
 }
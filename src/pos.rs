@@ -14,6 +14,7 @@ pub struct Spanned<T> {
 }
 
 pub const EMPTYSPAN: Span = Span {
+    file: FileId(0),
     start: Position {
         line: 1,
         column: 0,
@@ -29,6 +30,7 @@ pub const EMPTYSPAN: Span = Span {
 /// A span between two locations in a source file
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub struct Span {
+    pub file: FileId,
     pub start: Position,
     pub end: Position,
 }
@@ -81,6 +83,56 @@ impl Position {
     }
 }
 
+/// Identifies a file in a `SourceMap`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileId(pub u32);
+
+/// Every file diagnostics can point into, looked up by the `FileId` in their spans
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub name: String,
+    pub source: String,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap { files: Vec::new() }
+    }
+
+    pub fn add<T: Into<String>>(&mut self, name: T, source: String) -> FileId {
+        self.files.push(SourceFile {
+            name: name.into(),
+            source,
+        });
+
+        FileId(self.files.len() as u32 - 1)
+    }
+
+    pub fn get(&self, file: FileId) -> Option<&SourceFile> {
+        self.files.get(file.0 as usize)
+    }
+
+    /// The name of the file, or `<unknown>` for an id from another map
+    pub fn name(&self, file: FileId) -> &str {
+        self.get(file).map_or("<unknown>", |file| &file.name)
+    }
+
+    pub fn source(&self, file: FileId) -> &str {
+        self.get(file).map_or("", |file| &file.source)
+    }
+}
+
+impl Span {
+    pub fn new(file: FileId, start: Position, end: Position) -> Self {
+        Span { file, start, end }
+    }
+}
+
 impl Display for Position {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{},{}", self.line, self.column)
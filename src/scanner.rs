@@ -1,12 +1,24 @@
-use error::Reporter;
-use pos::{CharPosition, Position, Span, Spanned};
+use codes;
+use error::{Diagnostic, Level, Reporter};
+use pos::{CharPosition, FileId, Position, Span, Spanned};
+use std::borrow::Cow;
 use std::fmt::{self, Display};
-use token::{Token, TokenType};
+use std::mem;
+use token::{Token, TokenIter, TokenType, TokenWithTrivia, Trivia, TriviaKind};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum LexerError {
     UnclosedString,
     UnclosedBlockComment,
+    UnclosedInterpolation,
+    EmptyInterpolation,
+    InvalidEscape(char),
+    InvalidUnicodeEscape,
+    InvalidDigit(char, u32),
+    InvalidSeparator,
+    MissingDigits,
+    MissingExponent,
+    NumberTooLarge,
     Unexpected(char, Position),
 }
 
@@ -18,10 +30,16 @@ pub struct Lexer<'a> {
     chars: CharPosition<'a>,
     lookahead: Option<(Position, char)>,
     end: Position,
+    /// The start of each `${` being lexed and the depth of braces opened inside it
+    interpolations: Vec<(Position, usize)>,
+    /// Whether `lex` keeps comment tokens, for tooling such as formatters and doc generators
+    keep_comments: bool,
+    /// The file being lexed, recorded in every span
+    file: FileId,
 }
 impl<'a> Lexer<'a> {
     /// Returns a new Lexer
-    pub fn new(input: &'a str, reporter: Reporter) -> Lexer {
+    pub fn new(input: &'a str, reporter: Reporter) -> Lexer<'a> {
         let mut chars = CharPosition::new(input);
         let end = chars.pos;
         Lexer {
@@ -30,9 +48,29 @@ impl<'a> Lexer<'a> {
             reporter,
             lookahead: chars.next(),
             chars,
+            interpolations: Vec::new(),
+            keep_comments: false,
+            file: FileId::default(),
         }
     }
 
+    /// Sets the file recorded in the spans of the tokens and diagnostics
+    pub fn file(mut self, file: FileId) -> Lexer<'a> {
+        self.file = file;
+        self
+    }
+
+    /// Makes `lex` return `Comment` and `DocComment` tokens instead of discarding them
+    pub fn keep_comments(mut self) -> Lexer<'a> {
+        self.keep_comments = true;
+        self
+    }
+
+    /// Lazily lexes the input losslessly, attaching whitespace and comments to the tokens as trivia
+    pub fn tokens(self) -> TokenIter<'a> {
+        TokenIter::new(self)
+    }
+
     fn advance(&mut self) -> Option<(Position, char)> {
         match self.lookahead {
             Some((pos, ch)) => {
@@ -45,18 +83,42 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn span_error<T: Into<String>>(&mut self, msg: T, start: Position, end: Position) {
-        self.reporter.error(msg.into(), Span { start, end })
+    /// Spans a token made of the single ASCII char at `start`
+    fn span(&self, token: TokenType<'a>, start: Position) -> Spanned<Token<'a>> {
+        self.spans(token, start, start.shift(' '))
     }
 
-    fn error<T: Into<String>>(&mut self, msg: T, pos: Position) {
-        self.reporter.error(
-            msg.into(),
-            Span {
-                start: pos,
-                end: pos,
-            },
-        )
+    fn spans(&self, token: TokenType<'a>, start: Position, end: Position) -> Spanned<Token<'a>> {
+        Spanned {
+            value: token_with_info(token),
+            span: Span::new(self.file, start, end),
+        }
+    }
+
+    fn span_error(&mut self, error: LexerError, start: Position, end: Position) {
+        let code = error.code();
+        let msg: String = error.into();
+
+        let span = Span::new(self.file, start, end);
+
+        self.reporter
+            .report(Diagnostic::new(Level::Error, msg, span).code(code))
+    }
+
+    /// Reports `error` and returns an `Error` token covering the input it was found in
+    fn error_token(
+        &mut self,
+        error: LexerError,
+        start: Position,
+        end: Position,
+    ) -> Spanned<Token<'a>> {
+        self.span_error(error.clone(), start, end);
+
+        self.spans(TokenType::Error(error), start, end)
+    }
+
+    fn error(&mut self, error: LexerError, pos: Position) {
+        self.span_error(error, pos, pos)
     }
 
     fn slice(&self, start: Position, end: Position) -> &'a str {
@@ -81,245 +143,547 @@ impl<'a> Lexer<'a> {
     where
         F: FnMut(char) -> bool,
     {
-        self.lookahead.map_or(false, |(_, ch)| check(ch))
+        self.lookahead.is_some_and(|(_, ch)| check(ch))
     }
 
-    fn line_comment(&mut self, start: Position) {
-        let (_, _) = self.take_whilst(start, |ch| ch != '\n');
-    }
+    /// Consumes whitespace and comments, stopping at a newline when lexing `trailing` trivia
+    fn trivia(&mut self, trailing: bool) -> Vec<Trivia<'a>> {
+        let mut trivia = vec![];
 
-    fn block_comment(&mut self, start: Position) -> Result<(), ()> {
-        self.advance(); // Eats the '*'
-        loop {
-            self.advance(); // Eats the '*'
+        while let Some((start, ch)) = self.lookahead {
+            let rest = &self.input[start.absolute..];
+
+            let kind = match ch {
+                '\n' if trailing => break,
+                '\n' => {
+                    self.advance();
+                    TriviaKind::Newline
+                }
+                ch if ch.is_whitespace() => {
+                    self.take_whilst(start, |ch| ch.is_whitespace() && ch != '\n');
+                    TriviaKind::Whitespace
+                }
+                '/' if rest.starts_with("//") => {
+                    self.advance();
 
-            match self.lookahead {
-                Some((_, '/')) => {
+                    match self.line_comment(start).value.ty {
+                        TokenType::DocComment(_) => TriviaKind::DocComment,
+                        _ => TriviaKind::Comment,
+                    }
+                }
+                '/' if rest.starts_with("/*") => {
                     self.advance();
-                    return Ok(());
+
+                    let _ = self.block_comment(start);
+                    TriviaKind::Comment
                 }
-                Some((_, _)) => continue,
+                _ => break,
+            };
+
+            trivia.push(Trivia {
+                kind,
+                text: self.slice(start, self.end),
+            });
+        }
+
+        trivia
+    }
+
+    /// Lexes the next token along with its trivia
+    pub(crate) fn next_with_trivia(&mut self) -> TokenWithTrivia<'a> {
+        let leading = self.trivia(false);
+        let start = self.end;
+        let token = self.next();
+
+        if token.value.ty == TokenType::EOF {
+            self.unclosed_interpolations();
+        }
+
+        let text = self.slice(start, self.end);
+        let trailing = self.trivia(true);
+
+        TokenWithTrivia {
+            token,
+            text,
+            leading,
+            trailing,
+        }
+    }
 
-                None => {
-                    let msg: String = LexerError::UnclosedBlockComment.into();
+    /// Lexes a `//` comment, or a `///` doc comment which documents the declaration following it
+    fn line_comment(&mut self, start: Position) -> Spanned<Token<'a>> {
+        let (end, comment) = self.take_whilst(start, |ch| ch != '\n');
 
-                    self.span_error(msg, start, self.end);
-                    return Err(());
+        if comment.starts_with("///") && !comment.starts_with("////") {
+            self.spans(TokenType::DocComment(&comment[3..]), start, end)
+        } else {
+            self.spans(TokenType::Comment, start, end)
+        }
+    }
+
+    /// Lexes a `/* */` comment, which may contain nested block comments
+    fn block_comment(&mut self, start: Position) -> Spanned<Token<'a>> {
+        self.advance(); // Eats the '*'
+
+        let mut depth = 1;
+
+        while let Some((_, ch)) = self.advance() {
+            if ch == '/' && self.peek(|ch| ch == '*') {
+                self.advance();
+                depth += 1;
+            } else if ch == '*' && self.peek(|ch| ch == '/') {
+                self.advance();
+                depth -= 1;
+
+                if depth == 0 {
+                    return self.spans(TokenType::Comment, start, self.end);
                 }
             }
         }
+
+        let end = self.end;
+
+        self.error_token(LexerError::UnclosedBlockComment, start, end)
     }
 
-    fn string_literal(&mut self, start: Position) -> Result<Spanned<Token<'a>>, ()> {
+    /// Lexes a string starting at the opening `"`, or at the `}` closing an interpolation when `resumed`.
+    /// A string containing `${` is lexed as an `Interpolation` part for each embedded expression
+    /// followed by an `InterpolationEnd` for the text after the last one
+    fn string_literal(&mut self, start: Position, resumed: bool) -> Spanned<Token<'a>> {
+        // Both " and } are a single byte
+        let content = start.shift('"');
+        // Only allocated once an escape is found, along with where the text after it begins
+        let mut unescaped: Option<String> = None;
+        let mut rest = content;
+
         while let Some((next, ch)) = self.advance() {
-            match ch {
-                '"' => {
+            let rest_of_input = &self.input[next.absolute..];
+            let empty_interpolation =
+                rest_of_input.starts_with("${") && rest_of_input[2..].trim_start().starts_with('}');
+
+            if ch == '\\' {
+                let escaped = self.escape(next);
+                let buf = unescaped.get_or_insert_with(String::new);
+
+                buf.push_str(&self.input[rest.absolute..next.absolute]);
+                buf.extend(escaped);
+
+                rest = self.end;
+            } else if empty_interpolation {
+                // An empty `${}` is reported and skipped so the rest of the string still lexes
+                self.take_whilst(next, |ch| ch != '}');
+                self.advance(); // Eats the }
+
+                self.span_error(LexerError::EmptyInterpolation, next, self.end);
+            } else if ch == '"' || (ch == '$' && self.peek(|ch| ch == '{')) {
+                let part = match unescaped.take() {
+                    Some(mut buf) => {
+                        buf.push_str(self.slice(rest, next));
+                        Cow::Owned(buf)
+                    }
+                    None => Cow::Borrowed(self.slice(content, next)),
+                };
+
+                if ch == '"' {
                     let end = next.shift(ch);
                     // use next to include the end " and shift start to not include the first "
-                    return Ok(spans(
-                        TokenType::String(self.slice(start.shift('"'), next)),
-                        start,
-                        end,
-                    ));
+                    let token = if resumed {
+                        TokenType::InterpolationEnd(part)
+                    } else {
+                        TokenType::String(part)
+                    };
+
+                    return self.spans(token, start, end);
                 }
 
-                _ => (),
+                self.advance(); // Eats the {
+
+                let end = next.shift('$').shift('{');
+
+                self.interpolations.push((next, 0));
+
+                return self.spans(TokenType::Interpolation(part), start, end);
             }
         }
 
-        let msg: String = LexerError::UnclosedString.into();
+        let end = self.end;
+
+        self.error_token(LexerError::UnclosedString, start, end)
+    }
 
-        self.span_error(msg, start, self.end);
+    /// Lexes the escape sequence following the `\\` at `start`, returning the char it stands for.
+    /// Invalid escapes are reported and produce no char so the rest of the string is still lexed
+    fn escape(&mut self, start: Position) -> Option<char> {
+        let (_, ch) = self.lookahead?;
+
+        self.advance();
+
+        let escaped = match ch {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '$' => '$',
+            'u' => return self.unicode_escape(start),
+            ch => {
+                self.span_error(LexerError::InvalidEscape(ch), start, self.end);
+
+                return None;
+            }
+        };
 
-        Err(())
+        Some(escaped)
     }
 
-    fn number(&mut self, start: Position) -> Result<Spanned<Token<'a>>, ()> {
-        let (end, int) = self.take_whilst(start, |c| c.is_numeric());
+    /// Lexes the `{XXXX}` of a `\\u{XXXX}` escape, taking at most six hex digits
+    fn unicode_escape(&mut self, start: Position) -> Option<char> {
+        let mut code = None;
 
-        let (token, start, end) = match self.lookahead {
-            Some((_, '.')) => {
-                self.advance();
+        if self.peek(|ch| ch == '{') {
+            let (digits_start, _) = self.advance().unwrap();
+            let mut count = 0;
 
-                let (end, float) = self.take_whilst(start, |c| c.is_numeric());
+            let (_, digits) = self.take_whilst(digits_start.shift('{'), |ch| {
+                count += 1;
+                ch.is_ascii_hexdigit() && count <= 6
+            });
 
-                match self.lookahead {
-                    Some((_, ch)) if ch.is_alphabetic() => {
-                        let msg: String = LexerError::Unexpected(ch, start).into();
+            if !digits.is_empty() && self.peek(|ch| ch == '}') {
+                self.advance();
+                code = u32::from_str_radix(digits, 16).ok();
+            }
+        }
 
-                        self.error(msg, start);
+        match code.and_then(::std::char::from_u32) {
+            Some(ch) => Some(ch),
+            None => {
+                self.span_error(LexerError::InvalidUnicodeEscape, start, self.end);
 
-                        return Err(()); // Rejects floats like 10.k
-                    }
+                None
+            }
+        }
+    }
 
-                    _ => (
-                        TokenType::Number(float.parse().expect("An invalid float was used")),
-                        start,
-                        end,
-                    ),
-                }
+    /// Lexes a raw string starting at the `r`, in which backslashes and `${` are kept verbatim
+    fn raw_string_literal(&mut self, start: Position) -> Spanned<Token<'a>> {
+        let (content, _) = self.advance().unwrap(); // Eats the "
+        let content = content.shift('"');
+
+        while let Some((next, ch)) = self.advance() {
+            if ch == '"' {
+                return self.spans(
+                    TokenType::String(Cow::Borrowed(self.slice(content, next))),
+                    start,
+                    next.shift(ch),
+                );
             }
+        }
+
+        let end = self.end;
+
+        self.error_token(LexerError::UnclosedString, start, end)
+    }
+
+    /// Lexes a decimal number with an optional fraction and exponent, or a `0x`, `0b` or `0o` prefixed integer.
+    /// Digits may be separated by `_`
+    fn number(&mut self, start: Position, first: char) -> Spanned<Token<'a>> {
+        let radix = match self.lookahead {
+            Some((_, 'x')) if first == '0' => 16,
+            Some((_, 'b')) if first == '0' => 2,
+            Some((_, 'o')) if first == '0' => 8,
+            _ => 10,
+        };
+
+        if radix != 10 {
+            self.advance(); // Eats the prefix
+
+            let digits_start = self.end;
+            let (end, digits) = self.take_whilst(digits_start, is_letter_ch);
 
-            Some((_, ch)) if ch.is_alphabetic() => {
-                let msg: String = LexerError::Unexpected(ch, start).into();
-                self.error(msg, start);
+            if let Err(error) = self.check_digits(digits, digits_start, radix) {
+                return self.spans(TokenType::Error(error), start, end);
+            }
 
-                return Err(());
+            if digits.is_empty() {
+                return self.error_token(LexerError::MissingDigits, start, end);
             }
-            Some(_) | None => (
-                TokenType::Number(int.parse().expect("Coundln't parse the float")),
-                start,
-                end,
-            ),
+
+            return match u64::from_str_radix(&digits.replace('_', ""), radix) {
+                Ok(int) => self.spans(TokenType::Number(int as f32), start, end),
+                Err(_) => self.error_token(LexerError::NumberTooLarge, start, end),
+            };
+        }
+
+        let (mut end, _) = self.take_whilst(start, is_digit_ch);
+
+        // A fraction needs digits, so `1.` is the number followed by a `.`
+        let fraction = match self.lookahead {
+            Some((dot, '.')) => self.input[dot.absolute + 1..].starts_with(is_digit_ch),
+            _ => false,
         };
 
-        Ok(spans(token, start, end))
+        if fraction {
+            self.advance();
+            end = self.take_whilst(start, is_digit_ch).0;
+        }
+
+        if self.peek(|ch| ch == 'e' || ch == 'E') {
+            self.advance();
+
+            if self.peek(|ch| ch == '+' || ch == '-') {
+                self.advance();
+            }
+
+            let exponent_start = self.end;
+            let (exponent_end, exponent) = self.take_whilst(exponent_start, is_digit_ch);
+
+            if exponent.is_empty() {
+                return self.error_token(LexerError::MissingExponent, start, exponent_end);
+            }
+
+            end = exponent_end;
+        }
+
+        // Rejects numbers like 10.k or 1f
+        if let Some((pos, ch)) = self.lookahead {
+            if is_letter_ch(ch) {
+                self.error(LexerError::Unexpected(ch, pos), pos);
+
+                let (end, _) = self.take_whilst(pos, is_letter_ch);
+
+                return self.spans(
+                    TokenType::Error(LexerError::Unexpected(ch, pos)),
+                    start,
+                    end,
+                );
+            }
+        }
+
+        let number = self.slice(start, end);
+
+        if let Err(error) = self.check_digits(number, start, 10) {
+            return self.spans(TokenType::Error(error), start, end);
+        }
+
+        match number.replace('_', "").parse::<f32>() {
+            Ok(number) if number.is_infinite() => {
+                self.error_token(LexerError::NumberTooLarge, start, end)
+            }
+            Ok(number) => self.spans(TokenType::Number(number), start, end),
+            Err(_) => self.error_token(LexerError::Unexpected(first, start), start, end),
+        }
+    }
+
+    /// Reports the first digit not valid in `radix` or `_` not between digits.
+    /// The `.`, exponent and its sign are only allowed in decimal numbers
+    fn check_digits(
+        &mut self,
+        number: &str,
+        start: Position,
+        radix: u32,
+    ) -> Result<(), LexerError> {
+        let mut chars = number.chars().peekable();
+        let mut pos = start;
+        let mut previous = None;
+
+        while let Some(ch) = chars.next() {
+            let next = chars.peek().cloned();
+
+            let error = match ch {
+                '_' if !next.is_some_and(|ch| ch.is_digit(radix))
+                    || matches!(previous, Some('.' | 'e' | 'E' | '+' | '-')) =>
+                {
+                    Some(LexerError::InvalidSeparator)
+                }
+                '_' => None,
+                '.' | 'e' | 'E' | '+' | '-' if radix == 10 => None,
+                ch if ch.is_digit(radix) => None,
+                ch => Some(LexerError::InvalidDigit(ch, radix)),
+            };
+
+            if let Some(error) = error {
+                self.error(error.clone(), pos);
+                return Err(error);
+            }
+
+            pos = pos.shift(ch);
+            previous = Some(ch);
+        }
+
+        Ok(())
     }
 
     fn identifier(&mut self, start: Position) -> Spanned<Token<'a>> {
         let (end, ident) = self.take_whilst(start, is_letter_ch);
-        spans(look_up_identifier(ident), start, end)
+        self.spans(look_up_identifier(ident), start, end)
     }
 
-    pub fn next(&mut self) -> Result<Spanned<Token<'a>>, ()> {
+    pub fn next(&mut self) -> Spanned<Token<'a>> {
         while let Some((start, ch)) = self.advance() {
             return match ch {
-                '.' => Ok(span(TokenType::Dot, start)),
-                // '?' => Ok(span(TokenType::QUESTION, start)),
-                ';' => Ok(span(TokenType::Semicolon, start)),
-                '{' => Ok(span(TokenType::LBrace, start)),
-                '}' => Ok(span(TokenType::RBrace, start)),
-                '[' => Ok(span(TokenType::LBracket, start)),
-                ']' => Ok(span(TokenType::RBracket, start)),
-                '(' => Ok(span(TokenType::LParen, start)),
-                ')' => Ok(span(TokenType::RParen, start)),
-                ',' => Ok(span(TokenType::Comma, start)),
-                // ':' => Ok(span(TokenType::COLON, start)),
-                // '^' => Ok(span(TokenType::EXPONENTIAL, start)),
-                // '%' => Ok(span(TokenType::MODULO, start)),
-                '"' => match self.string_literal(start) {
-                    Ok(token) => Ok(token),
-                    Err(_) => continue,
+                '.' => self.span(TokenType::Dot, start),
+                // '?' => self.span(TokenType::QUESTION, start),
+                ';' => self.span(TokenType::Semicolon, start),
+                '{' => {
+                    if let Some(&mut (_, ref mut depth)) = self.interpolations.last_mut() {
+                        *depth += 1;
+                    }
+
+                    self.span(TokenType::LBrace, start)
+                }
+                '}' => match self.interpolations.last_mut() {
+                    Some(&mut (_, 0)) => {
+                        self.interpolations.pop();
+
+                        self.string_literal(start, true)
+                    }
+                    Some(&mut (_, ref mut depth)) => {
+                        *depth -= 1;
+                        self.span(TokenType::RBrace, start)
+                    }
+                    None => self.span(TokenType::RBrace, start),
                 },
+                '[' => self.span(TokenType::LBracket, start),
+                ']' => self.span(TokenType::RBracket, start),
+                '(' => self.span(TokenType::LParen, start),
+                ')' => self.span(TokenType::RParen, start),
+                ',' => self.span(TokenType::Comma, start),
+                ':' => self.span(TokenType::Colon, start),
+                // '^' => self.span(TokenType::EXPONENTIAL, start),
+                // '%' => self.span(TokenType::MODULO, start),
+                '"' => self.string_literal(start, false),
 
                 '=' => {
                     if self.peek(|ch| ch == '=') {
                         self.advance();
-                        Ok(spans(TokenType::EqualEqual, start, start.shift('=')))
+                        self.spans(TokenType::EqualEqual, start, start.shift('=').shift('='))
                     } else {
-                        Ok(span(TokenType::Equal, start))
+                        self.span(TokenType::Equal, start)
                     }
                 }
 
                 '+' => {
-                    Ok(span(TokenType::Plus, start))
+                    self.span(TokenType::Plus, start)
                     // if self.peek(|ch| ch == '=') {
                     //     self.advance();
-                    //     Ok(spans(TokenType::PLUSASSIGN, start, start.shift('=')))
+                    //     self.spans(TokenType::PLUSASSIGN, start, start.shift('='))
                     // } else {
-                    //     Ok(span(TokenType::PLUS, start))
+                    //     self.span(TokenType::PLUS, start)
                     // }
                 }
 
                 '-' => {
                     // if self.peek(|ch| ch == '=') {
                     //     self.advance();
-                    //     Ok(spans(TokenType::MINUSASSIGN, start, start.shift('=')))
+                    //     self.spans(TokenType::MINUSASSIGN, start, start.shift('='))
                     // } else if self.peek(|ch| ch == '>') {
                     //     self.advance();
-                    //     Ok(spans(TokenType::FRETURN, start, start.shift('>')))
+                    //     self.spans(TokenType::FRETURN, start, start.shift('>'))
                     // } else {
-                    Ok(span(TokenType::Minus, start))
+                    self.span(TokenType::Minus, start)
                     // }
                 }
 
                 '*' => {
                     // if self.peek(|ch| ch == '=') {
                     //     self.advance();
-                    //     Ok(spans(TokenType::STARASSIGN, start, start.shift('=')))
+                    //     self.spans(TokenType::STARASSIGN, start, start.shift('='))
                     // } else {
-                    Ok(span(TokenType::Star, start))
+                    self.span(TokenType::Star, start)
                     // }
                 }
 
                 '/' => {
                     if self.peek(|ch| ch == '/') {
-                        self.advance();
-                        self.line_comment(start);
-                        continue;
+                        self.line_comment(start)
                     } else if self.peek(|ch| ch == '*') {
-                        if let Err(_) = self.block_comment(start) {}
-                        continue;
+                        self.block_comment(start)
                     } else {
-                        Ok(span(TokenType::Slash, start))
+                        self.span(TokenType::Slash, start)
                     }
                 }
 
                 '!' => {
                     if self.peek(|ch| ch == '=') {
                         self.advance();
-                        Ok(spans(TokenType::BangEqual, start, start.shift('=')))
+                        self.spans(TokenType::BangEqual, start, start.shift('!').shift('='))
                     } else {
-                        Ok(span(TokenType::Bang, start))
+                        self.span(TokenType::Bang, start)
                     }
                 }
 
                 '>' => {
                     if self.peek(|ch| ch == '=') {
                         self.advance();
-                        Ok(spans(TokenType::GreaterEqual, start, start.shift('=')))
+                        self.spans(TokenType::GreaterEqual, start, start.shift('>').shift('='))
                     } else {
-                        Ok(span(TokenType::Greater, start))
+                        self.span(TokenType::Greater, start)
                     }
                 }
                 '<' => {
                     if self.peek(|ch| ch == '=') {
                         self.advance();
-                        Ok(spans(TokenType::LessEqual, start, start.shift('=')))
+                        self.spans(TokenType::LessEqual, start, start.shift('<').shift('='))
                     } else {
-                        Ok(span(TokenType::Less, start))
+                        self.span(TokenType::Less, start)
                     }
                 }
 
-                ch if ch.is_numeric() => self.number(start),
-                ch if is_letter_ch(ch) => Ok(self.identifier(start)),
+                'r' if self.peek(|ch| ch == '"') => self.raw_string_literal(start),
+                ch if ch.is_ascii_digit() => self.number(start, ch),
+                ch if is_letter_ch(ch) => self.identifier(start),
                 ch if ch.is_whitespace() => continue,
-                ch => {
-                    let msg: String = LexerError::Unexpected(ch, start).into();
-                    self.error(msg, start);
-
-                    continue;
-                }
+                ch => self.error_token(LexerError::Unexpected(ch, start), start, start.shift(ch)),
             };
         }
 
-        Ok(spans(TokenType::EOF, self.end, self.end))
+        self.spans(TokenType::EOF, self.end, self.end)
     }
 
-    pub fn lex(&mut self) -> Result<Vec<Spanned<Token<'a>>>, ()> {
+    fn unclosed_interpolations(&mut self) {
+        for (start, _) in self.interpolations.drain(..).collect::<Vec<_>>() {
+            self.span_error(LexerError::UnclosedInterpolation, start, self.end);
+        }
+    }
+
+    /// Lexes the whole input. Malformed input is reported and lexed as `Error` tokens
+    /// so the compiler can carry on and report every error in one pass
+    pub fn lex(&mut self) -> Vec<Spanned<Token<'a>>> {
         let mut tokens = vec![];
 
         while self.lookahead.is_some() {
-            match self.next() {
-                Ok(token) => tokens.push(token),
-                Err(_) => (),
+            let token = self.next();
+
+            if token.value.ty != TokenType::EOF {
+                tokens.push(token)
             }
         }
 
-        tokens.push(span(TokenType::EOF, self.end));
+        self.unclosed_interpolations();
 
-        tokens.retain(|t| t.value.ty != TokenType::Comment);
+        tokens.push(self.spans(TokenType::EOF, self.end, self.end));
 
-        self.reporter.set_end(Span {
-            start: self.end,
-            end: self.end,
-        });
+        let mut doc = vec![];
 
-        if self.reporter.has_error() {
-            Err(())
-        } else {
-            Ok(tokens)
+        for token in tokens.iter_mut() {
+            match token.value.ty {
+                TokenType::DocComment(line) => doc.push(line),
+                TokenType::Comment => (),
+                _ => token.value.doc = mem::take(&mut doc),
+            }
+        }
+
+        if !self.keep_comments {
+            tokens.retain(|t| !t.value.ty.is_comment());
         }
+
+        let end = Span::new(self.file, self.end, self.end);
+
+        self.reporter.set_end(end);
+
+        tokens
     }
 }
 
@@ -329,28 +693,17 @@ fn is_letter_ch(ch: char) -> bool {
 }
 
 #[inline]
-fn span(token: TokenType, start: Position) -> Spanned<Token> {
-    Spanned {
-        value: token_with_info(token),
-        span: Span { start, end: start },
-    }
-}
-
-#[inline]
-fn spans(token: TokenType, start: Position, end: Position) -> Spanned<Token> {
-    Spanned {
-        value: token_with_info(token),
-        span: Span { start, end },
-    }
+fn is_digit_ch(ch: char) -> bool {
+    ch.is_ascii_digit() || ch == '_'
 }
 
 #[inline]
 fn token_with_info(ty: TokenType) -> Token {
-    Token { ty }
+    Token { ty, doc: vec![] }
 }
 
 #[inline]
-fn look_up_identifier(id: &str) -> TokenType {
+fn look_up_identifier(id: &str) -> TokenType<'_> {
     match id {
         // Class
         "class" => TokenType::Class,
@@ -359,6 +712,8 @@ fn look_up_identifier(id: &str) -> TokenType {
         // Functions and vars
         "fun" => TokenType::Fun,
         "var" => TokenType::Var,
+        // Modules
+        "import" => TokenType::Import,
         // Control Flow
         "if" => TokenType::If,
         "else" => TokenType::Else,
@@ -380,11 +735,43 @@ fn look_up_identifier(id: &str) -> TokenType {
     }
 }
 
-impl Into<String> for LexerError {
-    fn into(self) -> String {
-        match self {
+impl LexerError {
+    pub fn code(&self) -> &'static str {
+        match *self {
+            LexerError::UnclosedString => codes::UNCLOSED_STRING,
+            LexerError::UnclosedBlockComment => codes::UNCLOSED_BLOCK_COMMENT,
+            LexerError::UnclosedInterpolation => codes::UNCLOSED_INTERPOLATION,
+            LexerError::EmptyInterpolation => codes::EMPTY_INTERPOLATION,
+            LexerError::InvalidEscape(_) => codes::UNKNOWN_ESCAPE,
+            LexerError::InvalidUnicodeEscape => codes::INVALID_UNICODE_ESCAPE,
+            LexerError::InvalidDigit(_, _) => codes::INVALID_DIGIT,
+            LexerError::InvalidSeparator => codes::INVALID_SEPARATOR,
+            LexerError::MissingDigits => codes::MISSING_DIGITS,
+            LexerError::MissingExponent => codes::MISSING_EXPONENT,
+            LexerError::NumberTooLarge => codes::NUMBER_TOO_LARGE,
+            LexerError::Unexpected(_, _) => codes::UNEXPECTED_CHAR,
+        }
+    }
+}
+
+impl From<LexerError> for String {
+    fn from(error: LexerError) -> String {
+        match error {
             LexerError::UnclosedString => "Unclosed string".into(),
             LexerError::UnclosedBlockComment => "Unclosed block comment".into(),
+            LexerError::UnclosedInterpolation => "Unclosed string interpolation".into(),
+            LexerError::EmptyInterpolation => "Expected an expression inside `${}`".into(),
+            LexerError::InvalidEscape(ref c) => format!("Unknown escape sequence `\\{}`", c),
+            LexerError::InvalidUnicodeEscape => {
+                "Invalid unicode escape, expected `\\u{XXXX}` with at most 6 hex digits".into()
+            }
+            LexerError::InvalidDigit(ref c, ref radix) => {
+                format!("Invalid digit '{}' in a base {} number", c, radix)
+            }
+            LexerError::InvalidSeparator => "A `_` separator must be between digits".into(),
+            LexerError::MissingDigits => "Expected digits after the number prefix".into(),
+            LexerError::MissingExponent => "Expected digits in the exponent".into(),
+            LexerError::NumberTooLarge => "Number literal is too large".into(),
             LexerError::Unexpected(ref c, _) => format!("Unexpected char '{}' ", c),
         }
     }
@@ -395,6 +782,17 @@ impl Display for LexerError {
         match *self {
             LexerError::UnclosedString => write!(f, "unclosed string"),
             LexerError::UnclosedBlockComment => write!(f, "unclosed block comment"),
+            LexerError::UnclosedInterpolation => write!(f, "unclosed string interpolation"),
+            LexerError::EmptyInterpolation => write!(f, "empty string interpolation"),
+            LexerError::InvalidEscape(ref c) => write!(f, "unknown escape \\{}", c),
+            LexerError::InvalidUnicodeEscape => write!(f, "invalid unicode escape"),
+            LexerError::InvalidDigit(ref c, ref radix) => {
+                write!(f, "invalid digit {} in base {}", c, radix)
+            }
+            LexerError::InvalidSeparator => write!(f, "invalid separator"),
+            LexerError::MissingDigits => write!(f, "missing digits"),
+            LexerError::MissingExponent => write!(f, "missing exponent"),
+            LexerError::NumberTooLarge => write!(f, "number too large"),
             LexerError::Unexpected(ref c, ref p) => write!(f, "Unexpected char {} on {}", c, p),
         }
     }
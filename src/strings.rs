@@ -0,0 +1,152 @@
+use error::RuntimeError;
+use object::ObjectType;
+use value::Value;
+use vm::VM;
+
+/// The longest string `repeat` makes, so a large count is an error rather than an abort
+const MAX_REPEAT_LEN: usize = 1 << 24;
+
+/// Defines the methods callable on strings. Every method receives the string as `args[0]`
+pub fn define_methods(vm: &mut VM) {
+    vm.define_method(ObjectType::String, "len", 0, |_, args| {
+        Ok(Value::number(receiver(args).chars().count() as f32))
+    });
+
+    vm.define_method(ObjectType::String, "upper", 0, |vm, args| {
+        Ok(vm.string(&receiver(args).to_uppercase()))
+    });
+
+    vm.define_method(ObjectType::String, "lower", 0, |vm, args| {
+        Ok(vm.string(&receiver(args).to_lowercase()))
+    });
+
+    vm.define_method(ObjectType::String, "trim", 0, |vm, args| {
+        Ok(vm.string(receiver(args).trim()))
+    });
+
+    vm.define_method(ObjectType::String, "split", 1, |vm, args| {
+        let separator = string("split", args, 1)?;
+
+        if separator.is_empty() {
+            return Err(RuntimeError::new(
+                "`split` cannot split on an empty string, use `chars` instead.",
+            ));
+        }
+
+        let items = receiver(args)
+            .split(separator)
+            .map(|part| vm.string(part))
+            .collect();
+
+        Ok(vm.list(items))
+    });
+
+    vm.define_method(ObjectType::String, "chars", 0, |vm, args| {
+        let items = receiver(args)
+            .chars()
+            .map(|ch| vm.string(&ch.to_string()))
+            .collect();
+
+        Ok(vm.list(items))
+    });
+
+    vm.define_method(ObjectType::String, "contains", 1, |_, args| {
+        let pattern = string("contains", args, 1)?;
+        Ok(Value::bool(receiver(args).contains(pattern)))
+    });
+
+    vm.define_method(ObjectType::String, "starts_with", 1, |_, args| {
+        let pattern = string("starts_with", args, 1)?;
+        Ok(Value::bool(receiver(args).starts_with(pattern)))
+    });
+
+    vm.define_method(ObjectType::String, "ends_with", 1, |_, args| {
+        let pattern = string("ends_with", args, 1)?;
+        Ok(Value::bool(receiver(args).ends_with(pattern)))
+    });
+
+    vm.define_method(ObjectType::String, "find", 1, |_, args| {
+        let pattern = string("find", args, 1)?;
+        let string = receiver(args);
+
+        // Indices are in chars rather than bytes so they agree with indexing and `substring`
+        let index = match string.find(pattern) {
+            Some(byte) => string[..byte].chars().count() as f32,
+            None => -1.0,
+        };
+
+        Ok(Value::number(index))
+    });
+
+    vm.define_method(ObjectType::String, "replace", 2, |vm, args| {
+        let from = string("replace", args, 1)?;
+        let to = string("replace", args, 2)?;
+
+        if from.is_empty() {
+            return Err(RuntimeError::new(
+                "`replace` cannot replace an empty string.",
+            ));
+        }
+
+        Ok(vm.string(&receiver(args).replace(from, to)))
+    });
+
+    vm.define_method(ObjectType::String, "repeat", 1, |vm, args| {
+        let string = receiver(args);
+        let count = index("repeat", args, 1)?;
+
+        match string.len().checked_mul(count) {
+            Some(len) if len <= MAX_REPEAT_LEN => Ok(vm.string(&string.repeat(count))),
+            _ => Err(RuntimeError::new(format!(
+                "`repeat` count {} makes a string longer than {} bytes.",
+                args[1], MAX_REPEAT_LEN
+            ))),
+        }
+    });
+
+    vm.define_method(ObjectType::String, "substring", 2, |vm, args| {
+        let string = receiver(args);
+        let start = index("substring", args, 1)?;
+        let end = index("substring", args, 2)?;
+        let len = string.chars().count();
+
+        if start > end || end > len {
+            return Err(RuntimeError::new(format!(
+                "`substring` range {}..{} is out of bounds for a string of length {}.",
+                start, end, len
+            )));
+        }
+
+        let substring: String = string.chars().skip(start).take(end - start).collect();
+
+        Ok(vm.string(&substring))
+    });
+}
+
+fn receiver(args: &[Value]) -> &str {
+    args[0]
+        .try_str()
+        .expect("String methods are only invoked on strings")
+}
+
+/// Returns the argument at `index` as a string, raising a runtime error if it's not one
+fn string<'a>(name: &str, args: &'a [Value], index: usize) -> Result<&'a str, RuntimeError> {
+    match args[index].try_str() {
+        Some(string) => Ok(string),
+        None => Err(RuntimeError::new(format!(
+            "`{}` expected a string but got `{}`.",
+            name, args[index]
+        ))),
+    }
+}
+
+/// Returns the argument at `position` as a non-negative whole number
+fn index(name: &str, args: &[Value], position: usize) -> Result<usize, RuntimeError> {
+    match args[position].try_number() {
+        Some(number) if number >= 0.0 && number.fract() == 0.0 => Ok(number as usize),
+        _ => Err(RuntimeError::new(format!(
+            "`{}` expected a non-negative whole number but got `{}`.",
+            name, args[position]
+        ))),
+    }
+}
@@ -0,0 +1,179 @@
+use value::{Value, ValueType};
+
+/// The table grows once it is this full
+const MAX_LOAD: f32 = 0.75;
+
+/// A hash table keyed by values, using open addressing with linear probing.
+/// Keys are compared with `Value::is_equal` so only strings, numbers, bools and nil can be keys.
+#[derive(Debug, Default)]
+pub struct Table {
+    entries: Vec<Entry>,
+    /// The number of full entries and tombstones
+    count: usize,
+    len: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Entry {
+    Empty,
+    /// Left behind by a removal so probing continues past the removed key
+    Tombstone,
+    Full(Value, Value),
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Table {
+            entries: Vec::new(),
+            count: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns true if the value can be used as a key
+    pub fn is_key(key: &Value) -> bool {
+        match key.ty() {
+            ValueType::Nil | ValueType::Bool => true,
+            ValueType::Number => !key.as_number().is_nan(),
+            ValueType::Object => key.is_string(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn get(&self, key: &Value) -> Option<Value> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        match self.entries[self.find(key)] {
+            Entry::Full(_, value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Inserts the pair returning true if the key wasn't already in the table
+    pub fn insert(&mut self, key: Value, value: Value) -> bool {
+        debug_assert!(Table::is_key(&key), "`{}` is not a valid key", key);
+
+        if (self.count + 1) as f32 > self.entries.len() as f32 * MAX_LOAD {
+            let capacity = if self.entries.len() < 8 {
+                8
+            } else {
+                self.entries.len() * 2
+            };
+
+            self.resize(capacity);
+        }
+
+        let index = self.find(&key);
+
+        let is_new = match self.entries[index] {
+            Entry::Full(..) => false,
+            Entry::Empty => {
+                self.count += 1;
+                true
+            }
+            Entry::Tombstone => true,
+        };
+
+        if is_new {
+            self.len += 1;
+        }
+
+        self.entries[index] = Entry::Full(key, value);
+
+        is_new
+    }
+
+    pub fn remove(&mut self, key: &Value) -> Option<Value> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        let index = self.find(key);
+
+        match self.entries[index] {
+            Entry::Full(_, value) => {
+                self.entries[index] = Entry::Tombstone;
+                self.len -= 1;
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (Value, Value)> + 'a {
+        self.entries.iter().filter_map(|entry| match *entry {
+            Entry::Full(key, value) => Some((key, value)),
+            _ => None,
+        })
+    }
+
+    /// Returns the index of the entry holding the key, or where it should be inserted
+    fn find(&self, key: &Value) -> usize {
+        let capacity = self.entries.len();
+        let mut index = hash(key) as usize % capacity;
+        let mut tombstone = None;
+
+        loop {
+            match self.entries[index] {
+                Entry::Empty => return tombstone.unwrap_or(index),
+                Entry::Tombstone => {
+                    if tombstone.is_none() {
+                        tombstone = Some(index)
+                    }
+                }
+                Entry::Full(ref other, _) => {
+                    if other.is_equal(key) {
+                        return index;
+                    }
+                }
+            }
+
+            index = (index + 1) % capacity;
+        }
+    }
+
+    fn resize(&mut self, capacity: usize) {
+        let entries = ::std::mem::replace(&mut self.entries, vec![Entry::Empty; capacity]);
+
+        self.count = 0;
+
+        for entry in entries {
+            if let Entry::Full(key, value) = entry {
+                let index = self.find(&key);
+                self.entries[index] = Entry::Full(key, value);
+                self.count += 1;
+            }
+        }
+    }
+}
+
+/// Hashes a key consistently with `Value::is_equal`
+fn hash(key: &Value) -> u32 {
+    match key.ty() {
+        ValueType::Nil => 0,
+        ValueType::Bool => key.as_bool() as u32 + 1,
+        ValueType::Number => {
+            let number = key.as_number();
+            // 0.0 and -0.0 are equal so they must hash the same
+            let number = if number == 0.0 { 0.0 } else { number };
+            fnv1a(&number.to_bits().to_le_bytes())
+        }
+        ValueType::Object => fnv1a(key.try_str().unwrap_or("").as_bytes()),
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u32 {
+    let mut hash: u32 = 2_166_136_261;
+
+    for byte in bytes {
+        hash ^= u32::from(*byte);
+        hash = hash.wrapping_mul(16_777_619);
+    }
+
+    hash
+}
@@ -1,17 +1,46 @@
 use pos::Spanned;
+use scanner::{Lexer, LexerError};
+use std::borrow::Cow;
 use std::fmt::{self, Display};
 use std::hash::{self, Hash};
 
-use std::vec::IntoIter;
-
 #[derive(Debug, PartialEq, Clone)]
 pub struct Token<'a> {
     pub ty: TokenType<'a>,
+    /// The text of each `///` comment right before the token, which it documents
+    pub doc: Vec<&'a str>,
 }
 
+/// A lazy iterator over every token of the input along with its trivia,
+/// ending with the `EOF` token. Writing out each token in order reconstructs the input exactly
 #[derive(Debug, Clone)]
 pub struct TokenIter<'a> {
-    pub iter: IntoIter<Spanned<Token<'a>>>,
+    lexer: Lexer<'a>,
+    done: bool,
+}
+
+/// A token along with the exact source text it was lexed from and the trivia around it.
+/// Trailing trivia runs up to the end of the line, the newline starts the next token's leading trivia
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenWithTrivia<'a> {
+    pub token: Spanned<Token<'a>>,
+    pub text: &'a str,
+    pub leading: Vec<Trivia<'a>>,
+    pub trailing: Vec<Trivia<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia<'a> {
+    pub kind: TriviaKind,
+    pub text: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    Newline,
+    Comment,
+    DocComment,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -24,6 +53,7 @@ pub enum TokenType<'a> {
     LBracket,
     RBracket,
     Comma,
+    Colon,
     Dot,
     Minus,
     Plus,
@@ -38,9 +68,18 @@ pub enum TokenType<'a> {
     GreaterEqual,
     Less,
     LessEqual,
-    String(&'a str),
+    /// Borrowed from the source unless escapes had to be unescaped
+    String(Cow<'a, str>),
+    /// The text of an interpolated string before a `${`
+    Interpolation(Cow<'a, str>),
+    /// The text of an interpolated string after the last `}`
+    InterpolationEnd(Cow<'a, str>),
     Number(f32),
     Comment,
+    /// The text after a `///`, emitted just before the declaration it documents
+    DocComment(&'a str),
+    /// Malformed input, which has already been reported
+    Error(LexerError),
     And,
     Class,
     Else,
@@ -48,6 +87,7 @@ pub enum TokenType<'a> {
     Fun,
     For,
     If,
+    Import,
     Nil,
     Or,
     Print,
@@ -64,11 +104,16 @@ pub enum TokenType<'a> {
 pub enum RuleToken {
     LParen,
     LBracket,
+    LBrace,
     Minus,
     Plus,
     Slash,
     Star,
     Literal,
+    Interpolation,
+    Error,
+    Ident,
+    Dot,
     None,
     Bang,
     Comparison,
@@ -98,20 +143,26 @@ impl<'a> Display for TokenType<'a> {
             TokenType::LessEqual => write!(f, "<="),    // <=
             TokenType::GreaterEqual => write!(f, "=>"), // =>
             TokenType::String(ref s) => write!(f, "{:?}", s),
-            TokenType::Comma => write!(f, ","),     // ,
-            TokenType::Comment => write!(f, "//"),  // //
+            TokenType::Interpolation(ref s) => write!(f, "{}${{", s),
+            TokenType::InterpolationEnd(ref s) => write!(f, "}}{}", s),
+            TokenType::Comma => write!(f, ","),    // ,
+            TokenType::Colon => write!(f, ":"),    // :
+            TokenType::Comment => write!(f, "//"), // //
+            TokenType::DocComment(s) => write!(f, "///{}", s),
+            TokenType::Error(ref e) => write!(f, "{}", e),
             TokenType::Semicolon => write!(f, ";"), //
             TokenType::LParen => write!(f, "("),    // (
             TokenType::RParen => write!(f, ")"),    // )
             TokenType::LBrace => write!(f, "{{"),   // {
             TokenType::RBrace => write!(f, "}}"),   // }
-            TokenType::LBracket => write!(f, "["),   // [
-            TokenType::RBracket => write!(f, "]"),   // ]
+            TokenType::LBracket => write!(f, "["),  // [
+            TokenType::RBracket => write!(f, "]"),  // ]
             // Keywords,
             TokenType::Fun => write!(f, "fun"),
             TokenType::Print => write!(f, "print"),
             TokenType::Var => write!(f, "var"),
             TokenType::If => write!(f, "if"),
+            TokenType::Import => write!(f, "import"),
             TokenType::Else => write!(f, "else"),
             TokenType::Return => write!(f, "return"),
             TokenType::True => write!(f, "true"),
@@ -128,11 +179,41 @@ impl<'a> Display for TokenType<'a> {
     }
 }
 
+impl<'a> TokenIter<'a> {
+    pub fn new(lexer: Lexer<'a>) -> Self {
+        TokenIter { lexer, done: false }
+    }
+}
+
 impl<'a> Iterator for TokenIter<'a> {
-    type Item = Spanned<Token<'a>>;
+    type Item = TokenWithTrivia<'a>;
+
+    fn next(&mut self) -> Option<TokenWithTrivia<'a>> {
+        if self.done {
+            return None;
+        }
+
+        let token = self.lexer.next_with_trivia();
+
+        self.done = token.token.value.ty == TokenType::EOF;
+
+        Some(token)
+    }
+}
+
+impl<'a> Display for TokenWithTrivia<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for trivia in &self.leading {
+            write!(f, "{}", trivia.text)?;
+        }
 
-    fn next(&mut self) -> Option<Spanned<Token<'a>>> {
-        self.iter.next()
+        write!(f, "{}", self.text)?;
+
+        for trivia in &self.trailing {
+            write!(f, "{}", trivia.text)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -151,10 +232,16 @@ impl<'a> Hash for TokenType<'a> {
 }
 
 impl<'a> TokenType<'a> {
+    pub fn is_comment(&self) -> bool {
+        matches!(*self, TokenType::Comment | TokenType::DocComment(_))
+    }
+
     pub fn rule(&self) -> RuleToken {
         match *self {
             TokenType::Number(_) => RuleToken::Literal,
             TokenType::String(_) => RuleToken::Literal,
+            TokenType::Interpolation(_) => RuleToken::Interpolation,
+            TokenType::Error(_) => RuleToken::Error,
             TokenType::False => RuleToken::Literal,
             TokenType::True => RuleToken::Literal,
             TokenType::Minus => RuleToken::Minus,
@@ -165,6 +252,7 @@ impl<'a> TokenType<'a> {
             TokenType::LParen => RuleToken::LParen,
             TokenType::RParen => RuleToken::None,
             TokenType::LBracket => RuleToken::LBracket,
+            TokenType::LBrace => RuleToken::LBrace,
             TokenType::RBracket => RuleToken::None,
             TokenType::Bang => RuleToken::Bang,
             TokenType::Less
@@ -174,8 +262,10 @@ impl<'a> TokenType<'a> {
             | TokenType::GreaterEqual => RuleToken::Comparison,
             TokenType::Equal | TokenType::EqualEqual => RuleToken::Equality,
             TokenType::Nil => RuleToken::Literal,
+            TokenType::Ident(_) => RuleToken::Ident,
+            TokenType::Dot => RuleToken::Dot,
             TokenType::Semicolon => RuleToken::None,
-            ref e => unimplemented!("{:?}", e),
+            _ => RuleToken::None,
         }
     }
 }
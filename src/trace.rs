@@ -0,0 +1,71 @@
+//! Logging of every instruction the vm runs, enabled with `VM::trace`.
+
+use chunks::Chunk;
+use std::io::Write;
+use std::path::Path;
+use value::Value;
+
+/// Writes a line per instruction run: the file and line it came from, its offset, name and
+/// operands, then the stack before it runs.
+///
+/// Scripts and modules are the only functions lox has, so they are what `only` filters on.
+pub struct Tracer {
+    out: Box<dyn Write>,
+    only: Option<String>,
+}
+
+impl Tracer {
+    pub fn new<W: Write + 'static>(out: W) -> Self {
+        Tracer {
+            out: Box::new(out),
+            only: None,
+        }
+    }
+
+    /// Only traces the script or module with this path or file name without its extension,
+    /// e.g. `util` for `lib/util.lox`
+    pub fn only<T: Into<String>>(mut self, name: T) -> Self {
+        self.only = Some(name.into());
+        self
+    }
+
+    /// Whether instructions of the file named `name` are traced
+    pub(crate) fn traces(&self, name: &str) -> bool {
+        match self.only {
+            Some(ref only) => {
+                only == name
+                    || Path::new(name)
+                        .file_stem()
+                        .is_some_and(|stem| stem == &only[..])
+            }
+            None => true,
+        }
+    }
+
+    /// Logs the instruction at `offset` with the values on the stack. Tracing never stops the
+    /// script, so failed writes are ignored.
+    pub(crate) fn instruction(
+        &mut self,
+        name: &str,
+        chunk: &Chunk,
+        offset: usize,
+        stack: &[Value],
+    ) {
+        let instruction = chunk.instruction(offset);
+        let line = chunk.lines.get(offset).cloned().unwrap_or(0);
+        let operation = instruction.operation().to_string();
+
+        let mut stack_values = String::new();
+
+        for value in stack {
+            stack_values.push_str(&format!("[{}]", value));
+        }
+
+        let trace = format!(
+            "{}:{} {:04} {:36} {}",
+            name, line, offset, operation, stack_values
+        );
+
+        let _ = writeln!(self.out, "{}", trace.trim_end());
+    }
+}
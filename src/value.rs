@@ -1,6 +1,7 @@
-use object::{Object, ObjectType, RawObject, StringObject};
+use object::{
+    ListObject, MapObject, ModuleObject, NativeObject, Object, ObjectType, RawObject, StringObject,
+};
 use std::fmt::{self, Debug, Display};
-use std::mem;
 
 /// Represents that types that are used in lox
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -20,6 +21,9 @@ pub union As {
     object: RawObject,
 }
 
+/// A value on the vm's stack. Objects are owned and collected by the vm, so a value holding
+/// one is only valid while it is reachable from the vm, e.g. for the duration of a native call.
+/// Values leave the vm as `HostValue`s.
 #[derive(Clone, Copy)]
 pub struct Value {
     val: As,
@@ -48,7 +52,7 @@ impl Value {
         }
     }
 
-    pub fn object(object: RawObject) -> Value {
+    pub(crate) fn object(object: RawObject) -> Value {
         Value {
             val: As { object },
             ty: ValueType::Object,
@@ -79,7 +83,7 @@ impl Value {
         unsafe { self.val.number }
     }
 
-    pub fn as_object(&self) -> RawObject {
+    pub(crate) fn as_object(&self) -> RawObject {
         debug_assert_eq!(
             self.ty,
             ValueType::Object,
@@ -91,10 +95,71 @@ impl Value {
         unsafe { self.val.object }
     }
 
-    pub fn as_string<'a>(&self) -> &StringObject<'a> {
+    pub(crate) fn as_string<'a>(&self) -> &StringObject<'a> {
         let ptr = self.as_object();
 
-        unsafe { mem::transmute(ptr) }
+        unsafe { &*(ptr as *const StringObject) }
+    }
+
+    pub(crate) fn as_native(&self) -> &NativeObject {
+        let ptr = self.as_object();
+
+        unsafe { &*(ptr as *const NativeObject) }
+    }
+
+    pub(crate) fn as_module(&self) -> &ModuleObject {
+        let ptr = self.as_object();
+
+        unsafe { &*(ptr as *const ModuleObject) }
+    }
+
+    pub(crate) fn as_list(&self) -> &ListObject {
+        let ptr = self.as_object();
+
+        unsafe { &*(ptr as *const ListObject) }
+    }
+
+    /// Lists are shared by every value pointing at them so mutating one is visible through all of them
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) fn as_list_mut(&self) -> &mut ListObject {
+        let ptr = self.as_object();
+
+        unsafe { &mut *(ptr as *mut ListObject) }
+    }
+
+    pub fn ty(&self) -> ValueType {
+        self.ty
+    }
+
+    pub(crate) fn obj_ty(&self) -> ObjectType {
+        unsafe { (*self.as_object()).ty }
+    }
+
+    /// Returns the number if the value is a number
+    pub fn try_number(&self) -> Option<f32> {
+        if self.is_number() {
+            Some(self.as_number())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the bool if the value is a bool
+    pub fn try_bool(&self) -> Option<bool> {
+        if self.is_bool() {
+            Some(self.as_bool())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the contents if the value is a string
+    pub(crate) fn try_str(&self) -> Option<&str> {
+        if self.is_string() {
+            Some(self.as_string().chars.string())
+        } else {
+            None
+        }
     }
 
     pub fn is_number(&self) -> bool {
@@ -117,14 +182,41 @@ impl Value {
         self.ty == ValueType::Object
     }
 
-    pub fn is_string(&self) -> bool {
-        unsafe {
-            self.is_object()
-                && mem::transmute::<RawObject, &Object>(self.as_object()).ty == ObjectType::String
-        }
+    pub(crate) fn is_string(&self) -> bool {
+        unsafe { self.is_object() && (*self.as_object()).ty == ObjectType::String }
+    }
+
+    pub(crate) fn is_native(&self) -> bool {
+        unsafe { self.is_object() && (*self.as_object()).ty == ObjectType::Native }
+    }
+
+    pub(crate) fn is_module(&self) -> bool {
+        unsafe { self.is_object() && (*self.as_object()).ty == ObjectType::Module }
+    }
+
+    pub(crate) fn as_map(&self) -> &MapObject {
+        let ptr = self.as_object();
+
+        unsafe { &*(ptr as *const MapObject) }
     }
 
-    pub fn is_equal(&self, other: &Value) -> bool {
+    /// Maps are shared by every value pointing at them so mutating one is visible through all of them
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) fn as_map_mut(&self) -> &mut MapObject {
+        let ptr = self.as_object();
+
+        unsafe { &mut *(ptr as *mut MapObject) }
+    }
+
+    pub(crate) fn is_map(&self) -> bool {
+        unsafe { self.is_object() && (*self.as_object()).ty == ObjectType::Map }
+    }
+
+    pub(crate) fn is_list(&self) -> bool {
+        unsafe { self.is_object() && (*self.as_object()).ty == ObjectType::List }
+    }
+
+    pub(crate) fn is_equal(&self, other: &Value) -> bool {
         if self.ty != other.ty {
             false
         } else {
@@ -141,7 +233,7 @@ impl Value {
 
                         a_string.chars.string() == b_string.chars.string()
                     } else {
-                        false
+                        self.as_object() == other.as_object()
                     }
                 }
             }
@@ -149,6 +241,17 @@ impl Value {
     }
 }
 
+impl Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValueType::Bool => write!(f, "bool"),
+            ValueType::Nil => write!(f, "nil"),
+            ValueType::Number => write!(f, "number"),
+            ValueType::Object => write!(f, "object"),
+        }
+    }
+}
+
 impl Debug for Value {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         write!(fmt, "Value {{")?;
@@ -156,7 +259,7 @@ impl Debug for Value {
             if self.ty == ValueType::Number || self.ty == ValueType::Nil {
                 write!(fmt, "val:{:?},", self.val.number)?;
             } else if self.ty == ValueType::Object {
-                write!(fmt, "{}", self.as_string())?;
+                write!(fmt, "{}", self)?;
             } else {
                 write!(fmt, "val:{:?},", self.val.boolean)?;
             }
@@ -170,15 +273,19 @@ impl Debug for Value {
 impl Display for Value {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         unsafe {
-            if self.ty == ValueType::Number || self.ty == ValueType::Nil {
+            if self.ty == ValueType::Number {
                 write!(fmt, "{}", self.val.number)?;
             } else if self.ty == ValueType::Nil {
                 write!(fmt, "nil")?;
             } else if self.ty == ValueType::Object {
-                let obj: &Object = mem::transmute(self.as_object());
+                let obj: &Object = &*self.as_object();
 
                 match obj.ty {
                     ObjectType::String => write!(fmt, "{}", self.as_string())?,
+                    ObjectType::Native => write!(fmt, "{}", self.as_native())?,
+                    ObjectType::Module => write!(fmt, "{}", self.as_module())?,
+                    ObjectType::List => write!(fmt, "{}", self.as_list())?,
+                    ObjectType::Map => write!(fmt, "{}", self.as_map())?,
                 }
             } else {
                 write!(fmt, "{}", self.val.boolean)?;
@@ -188,3 +295,160 @@ impl Display for Value {
         Ok(())
     }
 }
+
+/// An owned copy of a value handed out by the vm, which stays valid after the vm collects
+/// the objects it was copied from or is dropped
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostValue {
+    Nil,
+    Bool(bool),
+    Number(f32),
+    String(String),
+    List(Vec<HostValue>),
+    /// The entries in the map's iteration order
+    Map(Vec<(HostValue, HostValue)>),
+    /// A native function by name
+    Native(String),
+    /// A module by name
+    Module(String),
+    /// A list inside itself, in place of the repeated list
+    ListCycle,
+    /// A map inside itself, in place of the repeated map
+    MapCycle,
+}
+
+impl Value {
+    /// Copies the value and every object reachable from it out of the vm
+    pub(crate) fn to_host(self) -> HostValue {
+        self.to_host_within(&mut Vec::new())
+    }
+
+    /// `containers` are the lists and maps being copied, to stop at cycles
+    fn to_host_within(self, containers: &mut Vec<RawObject>) -> HostValue {
+        match self.ty {
+            ValueType::Nil => return HostValue::Nil,
+            ValueType::Bool => return HostValue::Bool(self.as_bool()),
+            ValueType::Number => return HostValue::Number(self.as_number()),
+            ValueType::Object => (),
+        }
+
+        let object = self.as_object();
+
+        match self.obj_ty() {
+            ObjectType::String => HostValue::String(self.as_string().chars.string().into()),
+            ObjectType::Native => HostValue::Native(self.as_native().name.clone()),
+            ObjectType::Module => HostValue::Module(self.as_module().name.clone()),
+            ObjectType::List if containers.contains(&object) => HostValue::ListCycle,
+            ObjectType::Map if containers.contains(&object) => HostValue::MapCycle,
+            ObjectType::List => {
+                containers.push(object);
+
+                let items = self
+                    .as_list()
+                    .items
+                    .iter()
+                    .map(|item| item.to_host_within(containers))
+                    .collect();
+
+                containers.pop();
+                HostValue::List(items)
+            }
+            ObjectType::Map => {
+                containers.push(object);
+
+                let entries = self
+                    .as_map()
+                    .table
+                    .iter()
+                    .map(|(key, value)| {
+                        (
+                            key.to_host_within(containers),
+                            value.to_host_within(containers),
+                        )
+                    })
+                    .collect();
+
+                containers.pop();
+                HostValue::Map(entries)
+            }
+        }
+    }
+}
+
+impl HostValue {
+    pub fn try_number(&self) -> Option<f32> {
+        match *self {
+            HostValue::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    pub fn try_bool(&self) -> Option<bool> {
+        match *self {
+            HostValue::Bool(boolean) => Some(boolean),
+            _ => None,
+        }
+    }
+
+    pub fn try_str(&self) -> Option<&str> {
+        match *self {
+            HostValue::String(ref string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn is_nil(&self) -> bool {
+        *self == HostValue::Nil
+    }
+
+    /// Strings inside collections are quoted, like lox displays them
+    fn fmt_item(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HostValue::String(ref string) => write!(f, "{:?}", string),
+            ref other => write!(f, "{}", other),
+        }
+    }
+}
+
+impl Display for HostValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HostValue::Nil => write!(f, "nil"),
+            HostValue::Bool(boolean) => write!(f, "{}", boolean),
+            HostValue::Number(number) => write!(f, "{}", number),
+            HostValue::String(ref string) => write!(f, "{}", string),
+            HostValue::Native(ref name) => write!(f, "<native fn {}>", name),
+            HostValue::Module(ref name) => write!(f, "<module {}>", name),
+            HostValue::ListCycle => write!(f, "[...]"),
+            HostValue::MapCycle => write!(f, "{{...}}"),
+            HostValue::List(ref items) => {
+                write!(f, "[")?;
+
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    item.fmt_item(f)?;
+                }
+
+                write!(f, "]")
+            }
+            HostValue::Map(ref entries) => {
+                write!(f, "{{")?;
+
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    key.fmt_item(f)?;
+                    write!(f, ": ")?;
+                    value.fmt_item(f)?;
+                }
+
+                write!(f, "}}")
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Checks chunks loaded from outside the compiler before the vm runs them, so malformed
+//! bytecode is rejected instead of indexing out of bounds while running.
+//!
+//! The instruction set has no jumps, so execution reaches every instruction in order and the
+//! stack depth at each one is known statically.
+
+use chunks::Chunk;
+use op::{OpCode, Operand};
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+use vm::STACK_MAX;
+
+/// Why a chunk was rejected, and the offset of the instruction at fault
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyError {
+    pub offset: usize,
+    pub msg: String,
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid bytecode at offset {}: {}",
+            self.offset, self.msg
+        )
+    }
+}
+
+impl ::std::error::Error for VerifyError {}
+
+/// Verifies every instruction returning the most values the chunk can have on the stack
+pub(crate) fn verify(chunk: &Chunk) -> Result<usize, VerifyError> {
+    if chunk.lines.len() != chunk.code.len() {
+        let msg = format!(
+            "The line table has {} entries for {} bytes of code",
+            chunk.lines.len(),
+            chunk.code.len()
+        );
+        return Err(VerifyError { offset: 0, msg });
+    }
+
+    let mut offset = 0;
+    let mut depth = 0;
+    let mut max_depth = 0;
+    let mut last = None;
+
+    while offset < chunk.code.len() {
+        let error = |msg: String| Err(VerifyError { offset, msg });
+        let instruction = chunk.code[offset];
+
+        let opcode = match OpCode::try_from(instruction) {
+            Ok(opcode) => opcode,
+            Err(_) => return error(format!("Unknown opcode {:#04x}", instruction)),
+        };
+
+        let layout = opcode.operands();
+
+        if offset + layout.len() >= chunk.code.len() {
+            return error(format!("Missing operands of opcode {:#04x}", instruction));
+        }
+
+        let operands = &chunk.code[offset + 1..offset + 1 + layout.len()];
+
+        for (&kind, &operand) in layout.iter().zip(operands) {
+            if kind == Operand::Count {
+                continue;
+            }
+
+            let constant = match chunk.constants.get(operand as usize) {
+                Some(constant) => constant,
+                None => {
+                    let msg = format!(
+                        "Constant {} is out of bounds of the {} constants",
+                        operand,
+                        chunk.constants.len()
+                    );
+                    return error(msg);
+                }
+            };
+
+            if kind == Operand::Name && !constant.is_string() {
+                return error(format!("Constant {} is not a name", operand));
+            }
+        }
+
+        let (pops, pushes) = opcode.stack_effect(operands);
+
+        if pops > depth {
+            let msg = format!("Pops {} values with only {} on the stack", pops, depth);
+            return error(msg);
+        }
+
+        depth = depth - pops + pushes;
+        max_depth = max_depth.max(depth);
+
+        // The first slot of the stack is never used
+        if max_depth >= STACK_MAX {
+            let msg = format!("Needs more than the {} stack slots", STACK_MAX - 1);
+            return error(msg);
+        }
+
+        last = Some(opcode);
+        offset += 1 + layout.len();
+    }
+
+    if last != Some(OpCode::Return) {
+        let msg = "The chunk doesn't end with a return".into();
+        return Err(VerifyError { offset, msg });
+    }
+
+    Ok(max_depth)
+}
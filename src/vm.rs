@@ -1,66 +1,614 @@
+use bytecode::{self, LoadError};
 use chunks::Chunk;
-use object::{Object, RawObject, StringObject};
-use op::opcode;
+use compiler::Compiler;
+use error::{Error, Reporter, RuntimeError};
+use lists;
+use maps;
+use math;
+use native;
+use object::{
+    ListObject, MapObject, ModuleObject, NativeObject, Object, ObjectType, RawObject, StringObject,
+};
+use op::OpCode;
+use pos::{FileId, SourceMap};
+use scanner::Lexer;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fs;
+use std::iter;
 use std::mem;
-use value::Value;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use strings;
+use table::Table;
+use trace::Tracer;
+use value::{HostValue, Value};
+use verify;
 
-const STACK_MAX: usize = 256;
+pub(crate) const STACK_MAX: usize = 256;
+/// The number of allocations before the first collection
+const GC_THRESHOLD: usize = 1024;
 
-pub struct VM<'a> {
-    chunk: &'a Chunk,
+pub struct VM {
+    chunk: Chunk,
     stack: [Value; STACK_MAX],
     stack_top: usize,
     ip: usize,
+    ///  A linked list of all the objects allocated by the vm and the compilers it ran
     objects: RawObject,
+    globals: HashMap<String, Value>,
+    /// Every input evaluated by the vm. Strings in compiled chunks borrow from these,
+    /// so they must live as long as the vm
+    source_map: SourceMap,
+    /// The doc comments of the globals declared by evaluated input
+    docs: HashMap<String, String>,
+    /// The state of each chunk interrupted by an import or by a native evaluating more input,
+    /// restored once the inner chunk has run
+    suspended: Vec<Suspended>,
+    /// Every string allocated at runtime, so equal strings share one object
+    strings: HashMap<String, RawObject>,
+    /// The natives invoked by `receiver.method()`, receiving the receiver as their first argument
+    methods: HashMap<ObjectType, HashMap<String, Value>>,
+    /// Objects allocated since the last collection
+    allocations: usize,
+    next_gc: usize,
+    /// The diagnostics of the last evaluated input
+    diagnostics: Reporter,
+    deny_warnings: bool,
+    /// The file being run, which imports are resolved relative to
+    file: FileId,
+    /// Directories searched for imported modules after the directory of the importing file
+    search_paths: Vec<PathBuf>,
+    /// Every imported module by canonical path, so each is compiled and run once
+    modules: HashMap<PathBuf, Value>,
+    /// The canonical path of each file being run, outermost first, to detect cyclic imports
+    running: Vec<(PathBuf, FileId)>,
+    /// The globals defined by `new`, which every module starts out with
+    prelude: HashMap<String, Value>,
+    /// The names defined by the module being imported, which become its fields
+    exports: Option<HashSet<String>>,
+    tracer: Option<Tracer>,
 }
 
-pub enum VMResult {
-    RuntimeError,
-    Ok,
+/// What a chunk replaced in the vm while it runs
+struct Suspended {
+    chunk: Chunk,
+    ip: usize,
+    file: FileId,
+    /// The importer's globals, while a module runs with its own
+    globals: Option<HashMap<String, Value>>,
 }
 
-impl<'a> VM<'a> {
-    pub fn new(chunk: &'a Chunk, objects: RawObject) -> Self {
-        // let objects: RawObject = ptr::null::<Object>() as RawObject;
-        VM {
-            chunk,
+impl VM {
+    /// Creates a vm with the core natives (`clock`, `type`, `str`, `num`, `len` and `assert`)
+    /// and the `Math` module defined
+    pub fn new() -> Self {
+        let mut vm = VM {
+            chunk: Chunk::new(),
             ip: 0,
             stack_top: 1,
-            stack: [Value::nil(); 256],
-            objects,
+            stack: [Value::nil(); STACK_MAX],
+            objects: ptr::null_mut(),
+            globals: HashMap::new(),
+            source_map: SourceMap::new(),
+            suspended: Vec::new(),
+            docs: HashMap::new(),
+            strings: HashMap::new(),
+            methods: HashMap::new(),
+            allocations: 0,
+            next_gc: GC_THRESHOLD,
+            diagnostics: Reporter::new(),
+            deny_warnings: false,
+            file: FileId(0),
+            search_paths: Vec::new(),
+            modules: HashMap::new(),
+            running: Vec::new(),
+            prelude: HashMap::new(),
+            exports: None,
+            tracer: None,
+        };
+
+        native::define_core(&mut vm);
+        math::define_math(&mut vm);
+        strings::define_methods(&mut vm);
+        lists::define_methods(&mut vm);
+        maps::define_methods(&mut vm);
+
+        vm.prelude = vm.globals.clone();
+
+        vm
+    }
+
+    /// Logs every instruction run from now on with the tracer, or stops tracing with `None`
+    pub fn trace(&mut self, tracer: Option<Tracer>) {
+        self.tracer = tracer;
+    }
+
+    /// Adds a directory to search for modules not found next to the importing file
+    pub fn add_search_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.search_paths.push(path.into());
+    }
+
+    /// Makes warnings found while compiling fail evaluation like errors
+    pub fn deny_warnings(&mut self, deny: bool) {
+        self.deny_warnings = deny;
+    }
+
+    /// The warnings and errors found while compiling the last evaluated input
+    pub fn diagnostics(&self) -> &Reporter {
+        &self.diagnostics
+    }
+
+    /// Every input evaluated so far, for printing diagnostics
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    /// Lexes, compiles and runs the input returning the value of the last expression statement.
+    /// Globals defined by the input stay defined for later calls.
+    ///
+    /// The value is copied out of the vm, so it stays valid whatever the vm does next.
+    pub fn eval(&mut self, input: &str) -> Result<HostValue, Error> {
+        self.eval_file("<eval>", input)
+    }
+
+    /// Evaluates the input like `eval`, naming it `path` in diagnostics
+    pub fn eval_file(&mut self, path: &str, input: &str) -> Result<HostValue, Error> {
+        let file = self.source_map.add(path, input.into());
+        let reporter = Reporter::new();
+
+        reporter.deny_warnings(self.deny_warnings);
+        self.diagnostics = reporter.clone();
+
+        let chunk = match self.compile(file, &reporter) {
+            Ok((chunk, docs)) => {
+                self.docs.extend(docs);
+                chunk
+            }
+            Err(()) => return Err(Error::Compile(reporter)),
+        };
+
+        self.execute(path, file, chunk)
+    }
+
+    /// Compiles the input like `eval_file` without running it.
+    ///
+    /// String constants of the chunk are owned by the vm, and may be freed once it runs
+    /// another chunk.
+    pub(crate) fn compile_chunk(&mut self, path: &str, input: &str) -> Result<Chunk, Error> {
+        let file = self.source_map.add(path, input.into());
+        let reporter = Reporter::new();
+
+        reporter.deny_warnings(self.deny_warnings);
+        self.diagnostics = reporter.clone();
+
+        self.compile(file, &reporter)
+            .map(|(chunk, _)| chunk)
+            .map_err(|()| Error::Compile(reporter))
+    }
+
+    /// Compiles the input like `eval_file` without running it, returning the chunk in the
+    /// `.loxc` format run by `eval_bytecode`
+    pub fn compile_bytecode(&mut self, path: &str, input: &str) -> Result<Vec<u8>, Error> {
+        let chunk = self.compile_chunk(path, input)?;
+        let mut bytes = vec![];
+
+        bytecode::write(&chunk, &mut bytes)
+            .expect("The compiler emitted an unserializable constant");
+
+        Ok(bytes)
+    }
+
+    /// Compiles the input like `eval_file` without running it, returning the listing of its
+    /// instructions
+    pub fn disassemble(&mut self, path: &str, input: &str) -> Result<String, Error> {
+        let chunk = self.compile_chunk(path, input)?;
+
+        Ok(chunk.disassemble(path).to_string())
+    }
+
+    /// Decodes and verifies a chunk in the `.loxc` format without running it, returning the
+    /// listing of its instructions
+    pub fn disassemble_bytecode(&mut self, path: &str, bytes: &[u8]) -> Result<String, Error> {
+        let chunk = self.load_chunk(bytes).map_err(Error::Load)?;
+
+        Ok(chunk.disassemble(path).to_string())
+    }
+
+    /// Runs a chunk in the `.loxc` format without lexing or compiling it.
+    /// Its imports are resolved relative to `path`.
+    pub fn eval_bytecode(&mut self, path: &str, bytes: &[u8]) -> Result<HostValue, Error> {
+        let chunk = self.load_chunk(bytes).map_err(Error::Load)?;
+        let file = self.source_map.add(path, String::new());
+        let reporter = Reporter::new();
+
+        reporter.deny_warnings(self.deny_warnings);
+        self.diagnostics = reporter;
+
+        self.execute(path, file, chunk)
+    }
+
+    /// Runs the chunk compiled from the file at `path`
+    fn execute(&mut self, path: &str, file: FileId, chunk: Chunk) -> Result<HostValue, Error> {
+        let reporter = self.diagnostics.clone();
+        let running = Path::new(path)
+            .canonicalize()
+            .map(|canonical| (canonical, file))
+            .into_iter()
+            .collect();
+
+        // A native may be evaluating this while another file is running
+        let running = mem::replace(&mut self.running, running);
+        let result = self.interpret(file, chunk);
+
+        self.running = running;
+
+        match result {
+            // Copied out before anything else runs and collects its objects
+            Ok(value) => Ok(value.to_host()),
+            // A module it imported failed to compile
+            Err(_) if reporter.has_error() => Err(Error::Compile(reporter)),
+            Err(e) => Err(Error::Runtime(e)),
         }
     }
 
-    pub fn interpret(&mut self) -> VMResult {
-        self.run();
-        VMResult::Ok
+    /// Decodes and verifies a `.loxc` chunk, allocating its strings in the vm
+    pub(crate) fn load_chunk(&mut self, bytes: &[u8]) -> Result<Chunk, LoadError> {
+        let chunk = bytecode::read(bytes, |string| {
+            let object = StringObject::from_owned(string, self.objects);
+            self.allocated(object)
+        })?;
+
+        verify::verify(&chunk).map_err(LoadError::Invalid)?;
+
+        Ok(chunk)
     }
 
-    pub fn run(&mut self) -> VMResult {
-        #[cfg(feature = "debug")]
-        self.chunk.disassemble("test");
+    /// Lexes and compiles a file of the source map, reporting diagnostics to `reporter`.
+    /// Returns the chunk and the doc comments of the globals it declares
+    fn compile(
+        &mut self,
+        file: FileId,
+        reporter: &Reporter,
+    ) -> Result<(Chunk, HashMap<String, String>), ()> {
+        // The heap buffer of a string never moves, and files are never removed
+        let input: &str = unsafe { &*(self.source_map.source(file) as *const str) };
 
-        loop {
-            if cfg!(feature = "stack") {
-                for byte in self.stack[1..self.stack_top].iter() {
-                    print!("[{}]", byte);
+        let tokens = Lexer::new(input, reporter.clone()).file(file).lex();
+
+        let mut compiler = Compiler::new(reporter.clone(), tokens);
+
+        compiler.objects = self.objects;
+
+        let result = compiler.compile();
+
+        self.objects = compiler.objects;
+
+        if result.is_err() || reporter.has_error() {
+            return Err(());
+        }
+
+        let docs = compiler
+            .docs
+            .drain()
+            .map(|(name, doc)| (name.to_owned(), doc))
+            .collect();
+        let chunk = compiler
+            .chunks
+            .pop()
+            .expect("The compiler didn't produce a chunk");
+
+        Ok((chunk, docs))
+    }
+
+    /// Returns the module object of an imported file, compiling and running it the first time.
+    ///
+    /// The module runs with its own globals, starting with the prelude, and the globals it
+    /// defines become the fields of the module.
+    fn import(&mut self, path: &str) -> Result<Value, RuntimeError> {
+        let (name, canonical) = match self.resolve(path) {
+            Some(found) => found,
+            None => return self.runtime_error(&format!("Cannot find module `{}`.", path)),
+        };
+
+        if let Some(module) = self.modules.get(&canonical) {
+            return Ok(*module);
+        }
+
+        if let Some(start) = self.running.iter().position(|file| file.0 == canonical) {
+            let cycle = self.running[start..]
+                .iter()
+                .map(|&(_, file)| format!("`{}`", self.source_map.name(file)))
+                .chain(iter::once(format!("`{}`", name.display())))
+                .collect::<Vec<_>>();
+
+            let msg = format!("Cyclic import: {}.", cycle.join(" imports "));
+            return self.runtime_error(&msg);
+        }
+
+        let bytes = match fs::read(&name) {
+            Ok(bytes) => bytes,
+            Err(e) => return self.runtime_error(&format!("Cannot read module `{}`: {}.", path, e)),
+        };
+
+        // Modules can be precompiled too
+        let (file, chunk) = if bytecode::is_bytecode(&bytes) {
+            let chunk = match self.load_chunk(&bytes) {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    return self.runtime_error(&format!("Cannot load module `{}`: {}.", path, e))
+                }
+            };
+
+            (
+                self.source_map
+                    .add(name.display().to_string(), String::new()),
+                chunk,
+            )
+        } else {
+            let source = match String::from_utf8(bytes) {
+                Ok(source) => source,
+                Err(_) => {
+                    return self.runtime_error(&format!("Module `{}` is not valid utf-8.", path))
                 }
+            };
+
+            let file = self.source_map.add(name.display().to_string(), source);
+            let reporter = self.diagnostics.clone();
 
-                print!("\n")
+            match self.compile(file, &reporter) {
+                Ok((chunk, _)) => (file, chunk),
+                Err(()) => {
+                    return self.runtime_error(&format!("Cannot compile module `{}`.", path))
+                }
             }
-            match self.read_byte() {
-                opcode::RETURN => {
-                    println!("{}", self.pop());
-                    return VMResult::Ok;
+        };
+
+        // The module runs on top of the importer's stack
+        match verify::verify(&chunk) {
+            Ok(depth) if self.stack_top + depth > STACK_MAX => {
+                return self.runtime_error(&format!("Stack overflow importing `{}`.", path))
+            }
+            Ok(_) => (),
+            Err(e) => return self.runtime_error(&format!("Cannot load module `{}`: {}.", path, e)),
+        }
+
+        let prelude = self.prelude.clone();
+
+        self.suspend(file, chunk, Some(prelude));
+        self.running.push((canonical.clone(), file));
+
+        let exports = self.exports.replace(HashSet::new());
+        let result = self.run();
+        let exports = mem::replace(&mut self.exports, exports).unwrap_or_default();
+
+        let mut globals = self.resume().expect("The importer's globals weren't saved");
+        self.running.pop();
+
+        result?;
+
+        let fields = exports
+            .into_iter()
+            .filter_map(|name| globals.remove_entry(&name))
+            .collect();
+
+        let name = name
+            .file_stem()
+            .map_or("", |stem| stem.to_str().unwrap_or(""));
+        let object = ModuleObject::new(name, fields, self.objects);
+        let module = self.allocated(object);
+
+        self.modules.insert(canonical, module);
+
+        Ok(module)
+    }
+
+    /// Finds an imported file next to the importing file or else in a search path,
+    /// returning the path it was found at and its canonical path
+    fn resolve(&self, path: &str) -> Option<(PathBuf, PathBuf)> {
+        let importer = Path::new(self.source_map.name(self.file));
+        let dir = importer.parent().unwrap_or_else(|| Path::new(""));
+
+        iter::once(dir)
+            .chain(self.search_paths.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(path))
+            .find(|file| file.is_file())
+            .and_then(|file| file.canonicalize().ok().map(|canonical| (file, canonical)))
+    }
+
+    /// Runs a chunk on top of the stack. When a native is evaluating input, the chunk that
+    /// called it is suspended until this one returns.
+    fn interpret(&mut self, file: FileId, chunk: Chunk) -> Result<Value, RuntimeError> {
+        let overflows = match verify::verify(&chunk) {
+            Ok(depth) => self.stack_top + depth > STACK_MAX,
+            // Chunks are compiled or verified when loaded, so only their depth can be rejected
+            Err(_) => true,
+        };
+
+        if overflows {
+            return Err(RuntimeError {
+                msg: "Stack overflow.".into(),
+                path: self.source_map.name(file).into(),
+                line: chunk.lines.first().cloned().unwrap_or(0),
+            });
+        }
+
+        let base = self.stack_top;
+
+        self.suspend(file, chunk, None);
+
+        let result = self.run();
+
+        if result.is_err() {
+            self.stack_top = base;
+        }
+
+        self.resume();
+
+        result
+    }
+
+    /// Saves the running chunk and starts running `chunk` from its first instruction,
+    /// with its own `globals` if given
+    fn suspend(&mut self, file: FileId, chunk: Chunk, globals: Option<HashMap<String, Value>>) {
+        let globals = globals.map(|globals| mem::replace(&mut self.globals, globals));
+
+        self.suspended.push(Suspended {
+            chunk: mem::replace(&mut self.chunk, chunk),
+            ip: mem::replace(&mut self.ip, 0),
+            file: mem::replace(&mut self.file, file),
+            globals,
+        });
+    }
+
+    /// Restores the chunk suspended last, returning the globals of the chunk that ran if it
+    /// had its own
+    fn resume(&mut self) -> Option<HashMap<String, Value>> {
+        let suspended = self.suspended.pop().expect("No chunk was suspended");
+
+        self.chunk = suspended.chunk;
+        self.ip = suspended.ip;
+        self.file = suspended.file;
+
+        suspended
+            .globals
+            .map(|globals| mem::replace(&mut self.globals, globals))
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.globals.insert(name.into(), value);
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<HostValue> {
+        self.globals.get(name).map(|value| value.to_host())
+    }
+
+    /// The `///` comments before the declaration of a global in evaluated input, without
+    /// the slashes
+    pub fn doc(&self, name: &str) -> Option<&str> {
+        self.docs.get(name).map(String::as_str)
+    }
+
+    /// Copies a value out of the vm, e.g. to read the arguments of a native
+    pub fn to_host(&self, value: Value) -> HostValue {
+        value.to_host()
+    }
+
+    /// Defines a global function implemented in rust.
+    /// Calling it with a number of arguments other than `arity` is a runtime error.
+    pub fn define_native<F>(&mut self, name: &str, arity: usize, function: F)
+    where
+        F: Fn(&mut VM, &[Value]) -> Result<Value, RuntimeError> + 'static,
+    {
+        let native = self.native(name, arity, function);
+
+        self.set_global(name, native);
+    }
+
+    /// Allocates a new native function owned by the vm without defining it
+    pub(crate) fn native<F>(&mut self, name: &str, arity: usize, function: F) -> Value
+    where
+        F: Fn(&mut VM, &[Value]) -> Result<Value, RuntimeError> + 'static,
+    {
+        let object = NativeObject::new(name, arity, Box::new(function), self.objects);
+
+        self.allocated(object)
+    }
+
+    /// Defines a global module whose fields are accessed with `.`
+    pub fn define_module(&mut self, name: &str, fields: HashMap<String, Value>) {
+        let object = ModuleObject::new(name, fields, self.objects);
+        let module = self.allocated(object);
+
+        self.set_global(name, module);
+    }
+
+    /// Defines a method on objects of type `ty`. `arity` doesn't count the receiver
+    pub(crate) fn define_method<F>(&mut self, ty: ObjectType, name: &str, arity: usize, function: F)
+    where
+        F: Fn(&mut VM, &[Value]) -> Result<Value, RuntimeError> + 'static,
+    {
+        let native = self.native(name, arity, function);
+
+        self.methods
+            .entry(ty)
+            .or_default()
+            .insert(name.into(), native);
+    }
+
+    /// Allocates a new list owned by the vm.
+    ///
+    /// The collector only finds objects reachable from the vm, so allocating is private to
+    /// natives, whose results are pushed on the stack before the next collection.
+    pub(crate) fn list(&mut self, items: Vec<Value>) -> Value {
+        let object = ListObject::new(items, self.objects);
+
+        self.allocated(object)
+    }
+
+    /// Allocates a new map owned by the vm
+    pub(crate) fn map(&mut self, table: Table) -> Value {
+        let object = MapObject::new(table, self.objects);
+
+        self.allocated(object)
+    }
+
+    /// Returns the interned string, allocating a new string owned by the vm if needed
+    pub(crate) fn string(&mut self, string: &str) -> Value {
+        if let Some(object) = self.strings.get(string) {
+            return Value::object(*object);
+        }
+
+        let object = StringObject::from_owned(string.into(), self.objects);
+
+        self.strings.insert(string.into(), object);
+
+        self.allocated(object)
+    }
+
+    /// Links a freshly allocated object into the object list
+    fn allocated(&mut self, object: RawObject) -> Value {
+        self.objects = object;
+        self.allocations += 1;
+
+        Value::object(object)
+    }
+
+    fn run(&mut self) -> Result<Value, RuntimeError> {
+        loop {
+            // Only collect between instructions, where every live value is reachable from a root
+            if self.allocations > self.next_gc {
+                self.collect_garbage();
+            }
+
+            if let Some(ref mut tracer) = self.tracer {
+                let name = self.source_map.name(self.file);
+
+                if tracer.traces(name) {
+                    let stack = &self.stack[1..self.stack_top];
+                    tracer.instruction(name, &self.chunk, self.ip, stack);
+                }
+            }
+
+            let instruction = self.read_byte();
+
+            let opcode = match OpCode::try_from(instruction) {
+                Ok(opcode) => opcode,
+                Err(_) => return self.runtime_error("Unknown opcode."),
+            };
+
+            match opcode {
+                OpCode::Return => {
+                    return Ok(self.pop());
                 }
-                opcode::CONSTANT => {
+                OpCode::Constant => {
                     let constant = self.read_constant();
                     self.push(constant);
                 }
-                opcode::NIL => self.push(Value::nil()),
-                opcode::TRUE => self.push(Value::bool(true)),
-                opcode::FALSE => self.push(Value::bool(false)),
-                opcode::NEGATE => {
+                OpCode::Nil => self.push(Value::nil()),
+                OpCode::True => self.push(Value::bool(true)),
+                OpCode::False => self.push(Value::bool(false)),
+                OpCode::Negate => {
                     if !self.peek(1).is_number() {
                         return self.runtime_error("Unary `-` operand must be a number.");
                     }
@@ -68,37 +616,371 @@ impl<'a> VM<'a> {
                     let v = Value::number(-self.pop().as_number());
                     self.push(v);
                 }
-                opcode::ADD => binary_op!(+,number,self),
-                opcode::SUB => binary_op!(-,number,self),
-                opcode::MUL => binary_op!(*,number,self),
-                opcode::DIV => binary_op!(/,number,self),
-                opcode::NOT => {
+                OpCode::Add => binary_op!(+,number,self),
+                OpCode::Sub => binary_op!(-,number,self),
+                OpCode::Mul => binary_op!(*,number,self),
+                OpCode::Div => binary_op!(/,number,self),
+                OpCode::Not => {
                     let value = Value::bool(self.pop().is_falsey());
                     self.push(value);
                 }
-                opcode::EQUAL => {
+                OpCode::Equal => {
                     let b = self.pop();
                     let a = self.pop();
 
                     self.push(Value::bool(a.is_equal(&b)))
                 }
-                opcode::GREATER => binary_op!(>,bool,self),
-                opcode::LESS => binary_op!(<,bool,self),
-                opcode::INDEX => {
-                    let b = self.pop().as_number(); // index 
-                    let a = self.pop(); // string
-                    let a = a.as_string();
+                OpCode::Greater => binary_op!(>,bool,self),
+                OpCode::Less => binary_op!(<,bool,self),
+                OpCode::IndexGet => {
+                    let index = self.pop();
+                    let receiver = self.pop();
+
+                    let value = if receiver.is_list() {
+                        let items = &receiver.as_list().items;
+                        let index = self.index(index, items.len())?;
+
+                        items[index]
+                    } else if receiver.is_map() {
+                        match receiver.as_map().table.get(&index) {
+                            Some(value) => value,
+                            None => {
+                                let msg = format!("Key `{}` is not in the map.", index);
+                                return self.runtime_error(&msg);
+                            }
+                        }
+                    } else if receiver.is_string() {
+                        let string = receiver.as_string().chars.string();
+                        let index = self.index(index, string.chars().count())?;
+                        let ch = string.chars().nth(index).unwrap();
+
+                        self.string(&ch.to_string())
+                    } else {
+                        let msg = format!("`{}` cannot be indexed.", receiver);
+                        return self.runtime_error(&msg);
+                    };
+
+                    self.push(value)
+                }
+                OpCode::Import => {
+                    let path = self.read_constant();
+                    let path = path.as_string().chars.string().to_owned();
+
+                    let module = self.import(&path)?;
+                    self.push(module);
+                }
+                OpCode::IndexSet => {
+                    let value = self.pop();
+                    let index = self.pop();
+                    let receiver = self.pop();
+
+                    if receiver.is_map() {
+                        self.check_key(&index)?;
+                        receiver.as_map_mut().table.insert(index, value);
+                    } else if receiver.is_list() {
+                        let items = &mut receiver.as_list_mut().items;
+                        let index = self.index(index, items.len())?;
+
+                        items[index] = value;
+                    } else {
+                        let msg = format!("`{}` does not support index assignment.", receiver);
+                        return self.runtime_error(&msg);
+                    }
+
+                    self.push(value)
+                }
+                OpCode::BuildList => {
+                    let count = self.read_byte() as usize;
+                    let items = self.stack[self.stack_top - count..self.stack_top].to_vec();
+
+                    self.stack_top -= count;
+
+                    let list = self.list(items);
+
+                    self.push(list)
+                }
+                OpCode::ToString => {
+                    if !self.peek(1).is_string() {
+                        let value = self.pop();
+                        let string = self.string(&value.to_string());
+
+                        self.push(string)
+                    }
+                }
+                OpCode::BuildMap => {
+                    let count = self.read_byte() as usize;
+                    let start = self.stack_top - count * 2;
+                    let mut table = Table::new();
+
+                    for i in 0..count {
+                        let key = self.stack[start + i * 2];
+                        let value = self.stack[start + i * 2 + 1];
+
+                        self.check_key(&key)?;
+                        table.insert(key, value);
+                    }
+
+                    self.stack_top = start;
+
+                    let map = self.map(table);
+
+                    self.push(map)
+                }
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::DefineGlobal => {
+                    let name = self.read_constant();
+                    let value = self.pop();
+                    let name = name.as_string().chars.string();
+
+                    if let Some(ref mut exports) = self.exports {
+                        exports.insert(name.into());
+                    }
+
+                    self.globals.insert(name.into(), value);
+                }
+                OpCode::GetGlobal => {
+                    let name = self.read_constant();
+
+                    let value = match self.globals.get(name.as_string().chars.string()) {
+                        Some(value) => *value,
+                        None => {
+                            let msg = format!(
+                                "Undefined variable `{}`.",
+                                name.as_string().chars.string()
+                            );
+                            return self.runtime_error(&msg);
+                        }
+                    };
+
+                    self.push(value)
+                }
+                OpCode::GetProperty => {
+                    let name = self.read_constant();
+                    let name = name.as_string().chars.string();
+
+                    if !self.peek(1).is_module() {
+                        let msg = format!("`{}` has no property `{}`.", self.peek(1), name);
+                        return self.runtime_error(&msg);
+                    }
+
+                    let receiver = self.pop();
+                    let module = receiver.as_module();
+
+                    let value = match module.fields.get(name) {
+                        Some(value) => *value,
+                        None => {
+                            let msg =
+                                format!("Module `{}` has no property `{}`.", module.name, name);
+                            return self.runtime_error(&msg);
+                        }
+                    };
+
+                    self.push(value)
+                }
+                OpCode::Invoke => {
+                    let name = self.read_constant();
+                    let arg_count = self.read_byte() as usize;
+
+                    self.invoke(name.as_string().chars.string(), arg_count)?;
+                }
+                OpCode::Call => {
+                    let arg_count = self.read_byte() as usize;
+                    self.call_value(arg_count)?;
+                }
+            }
+        }
+    }
+
+    fn call_value(&mut self, arg_count: usize) -> Result<(), RuntimeError> {
+        let callee = *self.peek(arg_count + 1);
+
+        if !callee.is_native() {
+            return self.runtime_error("Can only call functions.");
+        }
+
+        self.call_native(callee.as_native(), arg_count, false)
+    }
+
+    fn invoke(&mut self, name: &str, arg_count: usize) -> Result<(), RuntimeError> {
+        let receiver = *self.peek(arg_count + 1);
+
+        if receiver.is_module() {
+            let module = receiver.as_module();
+
+            let value = match module.fields.get(name) {
+                Some(value) => *value,
+                None => {
+                    let msg = format!("Module `{}` has no property `{}`.", module.name, name);
+                    return self.runtime_error(&msg);
+                }
+            };
+
+            let slot = self.stack_top - arg_count - 1;
+            self.stack[slot] = value;
+
+            return self.call_value(arg_count);
+        }
+
+        let method = if receiver.is_object() {
+            self.methods
+                .get(&receiver.obj_ty())
+                .and_then(|methods| methods.get(name))
+                .cloned()
+        } else {
+            None
+        };
+
+        match method {
+            Some(method) => self.call_native(method.as_native(), arg_count, true),
+            None => {
+                let msg = format!("`{}` has no method `{}`.", receiver, name);
+                self.runtime_error(&msg)
+            }
+        }
+    }
+
+    /// Calls a native with the arguments on top of the stack, replacing them and the callee
+    /// with the result. Methods are passed their receiver as the first argument
+    fn call_native(
+        &mut self,
+        native: &NativeObject,
+        arg_count: usize,
+        method: bool,
+    ) -> Result<(), RuntimeError> {
+        if arg_count != native.arity {
+            let msg = format!(
+                "`{}` expected {} arguments but got {}.",
+                native.name, native.arity, arg_count
+            );
+            return self.runtime_error(&msg);
+        }
+
+        let start = if method {
+            self.stack_top - arg_count - 1
+        } else {
+            self.stack_top - arg_count
+        };
+
+        let args = self.stack[start..self.stack_top].to_vec();
+
+        let result = match (native.function)(self, &args) {
+            Ok(result) => result,
+            Err(e) => return self.runtime_error(&e.msg),
+        };
+
+        self.stack_top -= arg_count + 1;
+        self.push(result);
 
+        Ok(())
+    }
+
+    /// Converts an index into a position in a sequence of length `len`,
+    /// with negative indices counting back from the end
+    fn index(&self, index: Value, len: usize) -> Result<usize, RuntimeError> {
+        if !index.is_number() || index.as_number().fract() != 0.0 {
+            let msg = format!("Index must be a whole number but got `{}`.", index);
+            return self.runtime_error(&msg);
+        }
 
-                    let ch = &a.chars.string()[b as usize..0];
+        let number = index.as_number();
+        let position = if number < 0.0 {
+            len as f32 + number
+        } else {
+            number
+        };
+
+        if position < 0.0 || position >= len as f32 {
+            let msg = format!("Index {} is out of bounds for length {}.", number, len);
+            return self.runtime_error(&msg);
+        }
 
-                    self.push(Value::object(StringObject::new(ch, self.objects)))
+        Ok(position as usize)
+    }
+
+    fn check_key(&self, key: &Value) -> Result<(), RuntimeError> {
+        if Table::is_key(key) {
+            Ok(())
+        } else {
+            let msg = format!("`{}` cannot be used as a map key.", key);
+            self.runtime_error(&msg)
+        }
+    }
 
-                    
+    fn collect_garbage(&mut self) {
+        let mut gray = Vec::new();
+
+        for value in self.stack[1..self.stack_top].iter() {
+            mark_value(*value, &mut gray);
+        }
+
+        for value in self.globals.values() {
+            mark_value(*value, &mut gray);
+        }
+
+        for value in self.chunk.constants.iter() {
+            mark_value(*value, &mut gray);
+        }
+
+        for methods in self.methods.values() {
+            for value in methods.values() {
+                mark_value(*value, &mut gray);
+            }
+        }
+
+        for suspended in self.suspended.iter() {
+            let constants = suspended.chunk.constants.iter();
+
+            for value in constants.chain(suspended.globals.iter().flat_map(HashMap::values)) {
+                mark_value(*value, &mut gray);
+            }
+        }
+
+        for value in self.prelude.values().chain(self.modules.values()) {
+            mark_value(*value, &mut gray);
+        }
+
+        while let Some(object) = gray.pop() {
+            trace(object, &mut gray);
+        }
+
+        // The intern table doesn't keep strings alive
+        self.strings
+            .retain(|_, object| unsafe { (**object).marked });
+
+        let live = self.sweep();
+
+        self.allocations = 0;
+        self.next_gc = ::std::cmp::max(live * 2, GC_THRESHOLD);
+    }
+
+    /// Frees every unmarked object returning the number of objects left
+    fn sweep(&mut self) -> usize {
+        let mut live = 0;
+        let mut previous: RawObject = ptr::null_mut();
+        let mut object = self.objects;
+
+        while !object.is_null() {
+            unsafe {
+                if (*object).marked {
+                    (*object).marked = false;
+                    live += 1;
+                    previous = object;
+                    object = (*object).next;
+                } else {
+                    object = Object::free(object);
+
+                    if previous.is_null() {
+                        self.objects = object;
+                    } else {
+                        (*previous).next = object;
+                    }
                 }
-                _ => return VMResult::RuntimeError,
             }
         }
+
+        live
     }
 
     fn concat(&mut self) {
@@ -114,23 +996,19 @@ impl<'a> VM<'a> {
         new.push_str(a.chars.string());
         new.push_str(b.chars.string());
 
-        #[cfg(feature = "debug")]
-        {
-            println!("{:?}", a.chars);
-            println!("{:?}", b.chars);
-        }
-
-        let result = StringObject::from_owned(new, self.objects);
+        let result = self.string(&new);
 
-        self.push(Value::object(result));
+        self.push(result);
     }
 
-    fn runtime_error(&self, msg: &str) -> VMResult {
-        let instructon = self.chunk.code.len() - self.ip;
+    fn runtime_error<T>(&self, msg: &str) -> Result<T, RuntimeError> {
+        let instruction = self.ip - 1;
 
-        eprintln!("[line {}] error: {}", self.chunk.lines[instructon], msg);
-
-        VMResult::RuntimeError
+        Err(RuntimeError {
+            msg: msg.into(),
+            path: self.source_map.name(self.file).into(),
+            line: self.chunk.lines[instruction],
+        })
     }
 
     fn read_byte(&mut self) -> u8 {
@@ -160,17 +1038,58 @@ impl<'a> VM<'a> {
     }
 }
 
-impl<'a> Drop for VM<'a> {
-    fn drop(&mut self) {
-        unsafe {
-            let mut object: Option<&Object> = mem::transmute(self.objects);
-    
-            while object.is_some() {
-
-                let next: &Object = mem::transmute(object.unwrap().next);
-                mem::drop(next);
-                object = Some(next);
+fn mark_value(value: Value, gray: &mut Vec<RawObject>) {
+    if !value.is_object() {
+        return;
+    }
+
+    let object = value.as_object();
+
+    unsafe {
+        if !(*object).marked {
+            (*object).marked = true;
+            gray.push(object);
+        }
+    }
+}
+
+/// Marks every object referenced by `object`
+fn trace(object: RawObject, gray: &mut Vec<RawObject>) {
+    let value = Value::object(object);
+
+    match value.obj_ty() {
+        ObjectType::String | ObjectType::Native => (),
+        ObjectType::Module => {
+            for field in value.as_module().fields.values() {
+                mark_value(*field, gray);
             }
         }
+        ObjectType::List => {
+            for item in value.as_list().items.iter() {
+                mark_value(*item, gray);
+            }
+        }
+        ObjectType::Map => {
+            for (key, value) in value.as_map().table.iter() {
+                mark_value(key, gray);
+                mark_value(value, gray);
+            }
+        }
+    }
+}
+
+impl Default for VM {
+    fn default() -> Self {
+        VM::new()
+    }
+}
+
+impl Drop for VM {
+    fn drop(&mut self) {
+        let mut object = self.objects;
+
+        while !object.is_null() {
+            object = unsafe { Object::free(object) };
+        }
     }
 }
@@ -0,0 +1,102 @@
+extern crate lox;
+
+use lox::bytecode::{self, LoadError, MAGIC, VERSION};
+use lox::{Error, VM};
+
+const INPUT: &str = "var a = \"x\" + \"y\";\na + str(1.5) + str(nil) + str(true);";
+
+fn compiled() -> Vec<u8> {
+    VM::new().compile_bytecode("test.lox", INPUT).unwrap()
+}
+
+fn load_error(bytes: &[u8]) -> LoadError {
+    match VM::new().disassemble_bytecode("test.loxc", bytes) {
+        Err(Error::Load(e)) => e,
+        other => panic!("Expected the chunk to be rejected, got {:?}", other),
+    }
+}
+
+#[test]
+fn round_trips_chunks() {
+    let mut vm = VM::new();
+    let bytes = vm.compile_bytecode("test.lox", INPUT).unwrap();
+
+    assert!(bytecode::is_bytecode(&bytes));
+    assert_eq!(
+        vm.disassemble_bytecode("test.lox", &bytes).unwrap(),
+        vm.disassemble("test.lox", INPUT).unwrap()
+    );
+
+    let value = VM::new().eval_bytecode("test.lox", &bytes).unwrap();
+    assert_eq!(value.try_str(), Some("xy1.5niltrue"));
+}
+
+#[test]
+fn rejects_other_files() {
+    assert!(!bytecode::is_bytecode(INPUT.as_bytes()));
+    assert_eq!(load_error(INPUT.as_bytes()), LoadError::NotBytecode);
+    assert_eq!(load_error(b""), LoadError::NotBytecode);
+
+    match VM::new().eval_bytecode("test.lox", b"LOX") {
+        Err(Error::Load(LoadError::NotBytecode)) => (),
+        other => panic!("Expected the file to be rejected, got {:?}", other),
+    }
+}
+
+#[test]
+fn rejects_other_versions() {
+    let mut bytes = compiled();
+    bytes[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&(VERSION - 1).to_le_bytes());
+
+    assert_eq!(
+        load_error(&bytes),
+        LoadError::UnsupportedVersion(VERSION - 1)
+    );
+    assert_eq!(
+        load_error(&bytes).to_string(),
+        format!(
+            "Bytecode version {} is not supported, expected version {}",
+            VERSION - 1,
+            VERSION
+        )
+    );
+}
+
+#[test]
+fn rejects_truncated_files() {
+    let bytes = compiled();
+
+    for len in MAGIC.len()..bytes.len() {
+        assert_eq!(
+            load_error(&bytes[..len]),
+            LoadError::UnexpectedEnd,
+            "{}",
+            len
+        );
+    }
+}
+
+#[test]
+fn rejects_trailing_bytes() {
+    let mut bytes = compiled();
+    bytes.push(0);
+
+    assert_eq!(load_error(&bytes), LoadError::TrailingBytes);
+}
+
+#[test]
+fn rejects_malformed_constants() {
+    let mut header = MAGIC.to_vec();
+    header.extend_from_slice(&VERSION.to_le_bytes());
+    // No code, then one constant
+    header.extend_from_slice(&[0, 0, 0, 0, 1, 0, 0, 0]);
+
+    let mut unknown = header.clone();
+    unknown.push(9);
+
+    let mut invalid = header.clone();
+    invalid.extend_from_slice(&[3, 2, 0, 0, 0, 0xc3, 0x28]);
+
+    assert_eq!(load_error(&unknown), LoadError::UnknownConstant(9));
+    assert_eq!(load_error(&invalid), LoadError::InvalidString);
+}
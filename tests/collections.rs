@@ -0,0 +1,218 @@
+extern crate lox;
+
+use lox::{Error, VM};
+
+/// The codes of the errors compiling the input
+fn error_codes(input: &str) -> Vec<&'static str> {
+    match VM::new().eval(input) {
+        Err(Error::Compile(reporter)) => reporter
+            .diagnostics()
+            .iter()
+            .filter_map(|diagnostic| diagnostic.error_code())
+            .collect(),
+        other => panic!("Expected a compile error, got {:?}", other),
+    }
+}
+
+fn eval(vm: &mut VM, input: &str) -> String {
+    vm.eval(input).unwrap().to_string()
+}
+
+fn runtime_error(vm: &mut VM, input: &str) -> String {
+    match vm.eval(input) {
+        Err(Error::Runtime(e)) => e.msg,
+        other => panic!("Expected a runtime error, got {:?}", other),
+    }
+}
+
+fn map_literal(entries: usize) -> String {
+    let entries = (0..entries)
+        .map(|i| format!("{}: {}", i, i))
+        .collect::<Vec<_>>();
+
+    format!("{{{}}};", entries.join(", "))
+}
+
+#[test]
+fn maps_can_start_statements() {
+    let mut vm = VM::new();
+
+    assert_eq!(vm.eval("{\"a\": 1};").unwrap().to_string(), "{\"a\": 1}");
+}
+
+#[test]
+fn map_literals_fit_on_the_stack() {
+    let mut vm = VM::new();
+    let map = vm.eval(&map_literal(127)).unwrap();
+
+    assert_eq!(map.to_string().matches(':').count(), 127);
+    assert_eq!(error_codes(&map_literal(128)), ["E0018"]);
+    assert_eq!(error_codes(&map_literal(200)), ["E0018"]);
+}
+
+#[test]
+fn collections_inside_themselves_are_displayed_once() {
+    let mut vm = VM::new();
+
+    vm.eval("var xs = [1];\nxs.push(xs);").unwrap();
+    vm.eval("var m = {\"self\": nil};\nm[\"self\"] = m;")
+        .unwrap();
+
+    assert_eq!(vm.eval("str(xs);").unwrap().to_string(), "[1, [...]]");
+    assert_eq!(vm.eval("str(m);").unwrap().to_string(), "{\"self\": {...}}");
+    assert_eq!(
+        vm.eval("\"${[xs, xs]}\";").unwrap().to_string(),
+        "[[1, [...]], [1, [...]]]"
+    );
+    assert_eq!(
+        vm.eval("str([m]);").unwrap().to_string(),
+        "[{\"self\": {...}}]"
+    );
+}
+
+#[test]
+fn lists_are_indexed_from_either_end() {
+    let mut vm = VM::new();
+
+    vm.eval("var xs = [1, \"two\", [3]];").unwrap();
+
+    assert_eq!(
+        eval(&mut vm, "[xs[0], xs[-1][0], xs[1]];"),
+        "[1, 3, \"two\"]"
+    );
+    assert_eq!(eval(&mut vm, "xs[1] = 2;"), "2");
+    assert_eq!(eval(&mut vm, "xs;"), "[1, 2, [3]]");
+    assert_eq!(eval(&mut vm, "[];"), "[]");
+    assert_eq!(eval(&mut vm, "[1, 2,];"), "[1, 2]");
+
+    assert_eq!(
+        runtime_error(&mut vm, "xs[3];"),
+        "Index 3 is out of bounds for length 3."
+    );
+    assert_eq!(
+        runtime_error(&mut vm, "xs[-4];"),
+        "Index -4 is out of bounds for length 3."
+    );
+    assert_eq!(
+        runtime_error(&mut vm, "xs[0.5];"),
+        "Index must be a whole number but got `0.5`."
+    );
+    assert_eq!(
+        runtime_error(&mut vm, "nil[0];"),
+        "`nil` cannot be indexed."
+    );
+}
+
+#[test]
+fn list_methods() {
+    let mut vm = VM::new();
+
+    vm.eval("var xs = [1];\nxs.push(3);\nxs.insert(1, 2);\nxs.insert(3, 4);")
+        .unwrap();
+
+    assert_eq!(eval(&mut vm, "xs;"), "[1, 2, 3, 4]");
+    assert_eq!(
+        eval(
+            &mut vm,
+            "[xs.pop(), xs.remove(0), xs.remove(-1), xs.len()];"
+        ),
+        "[4, 1, 3, 1]"
+    );
+    assert_eq!(
+        runtime_error(&mut vm, "[].pop();"),
+        "Cannot pop from an empty list."
+    );
+}
+
+#[test]
+fn lists_survive_collections() {
+    let mut vm = VM::new();
+
+    vm.eval("var xs = [];").unwrap();
+
+    for _ in 0..3000 {
+        vm.eval("var garbage = [\"a\" + \"b\", [1]];\nxs.push(\"item\" + str(len(xs)));")
+            .unwrap();
+    }
+
+    assert_eq!(eval(&mut vm, "len(xs);"), "3000");
+    assert_eq!(
+        eval(&mut vm, "xs[0] + xs[1500] + xs[-1];"),
+        "item0item1500item2999"
+    );
+}
+
+#[test]
+fn maps_are_indexed_by_key() {
+    let mut vm = VM::new();
+
+    vm.eval("var m = {\"a\": 1, 2: \"two\", true: nil};")
+        .unwrap();
+
+    assert_eq!(
+        eval(&mut vm, "[m[\"a\"], m[2], m[true]];"),
+        "[1, \"two\", nil]"
+    );
+    assert_eq!(eval(&mut vm, "m[\"b\"] = 3;"), "3");
+    assert_eq!(eval(&mut vm, "m[\"a\"] = 4;"), "4");
+    assert_eq!(eval(&mut vm, "[m[\"a\"], m[\"b\"], m.len()];"), "[4, 3, 4]");
+    assert_eq!(eval(&mut vm, "{};"), "{}");
+
+    assert_eq!(
+        runtime_error(&mut vm, "m[\"c\"];"),
+        "Key `c` is not in the map."
+    );
+    assert_eq!(
+        runtime_error(&mut vm, "m[[1]] = 1;"),
+        "`[1]` cannot be used as a map key."
+    );
+    assert_eq!(
+        runtime_error(&mut vm, "{[]: 1};"),
+        "`[]` cannot be used as a map key."
+    );
+}
+
+#[test]
+fn map_methods() {
+    let mut vm = VM::new();
+
+    vm.eval("var m = {\"a\": 1, \"b\": 2};").unwrap();
+
+    assert_eq!(
+        eval(&mut vm, "[m.has(\"a\"), m.has(\"c\")];"),
+        "[true, false]"
+    );
+    assert_eq!(eval(&mut vm, "len(m.keys()) + len(m.values());"), "4");
+    assert_eq!(eval(&mut vm, "m.remove(\"a\");"), "1");
+    assert_eq!(eval(&mut vm, "[m.keys(), m.values()];"), "[[\"b\"], [2]]");
+    assert_eq!(
+        runtime_error(&mut vm, "m.remove(\"a\");"),
+        "Key `a` is not in the map."
+    );
+    assert_eq!(
+        runtime_error(&mut vm, "m.has({});"),
+        "`has` expected a string, number, bool or nil key but got `{}`."
+    );
+}
+
+#[test]
+fn maps_survive_collections() {
+    let mut vm = VM::new();
+
+    vm.eval("var m = {};").unwrap();
+
+    for i in 0..3000 {
+        let input = format!(
+            "var garbage = {{\"a\" + \"b\": [1]}};\nm[\"key\" + str({})] = [\"value\" + str({})];",
+            i, i
+        );
+
+        vm.eval(&input).unwrap();
+    }
+
+    assert_eq!(eval(&mut vm, "m.len();"), "3000");
+    assert_eq!(
+        eval(&mut vm, "m[\"key0\"][0] + m[\"key2999\"][0];"),
+        "value0value2999"
+    );
+}
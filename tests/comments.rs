@@ -0,0 +1,88 @@
+extern crate lox;
+
+use lox::error::Reporter;
+use lox::scanner::{Lexer, LexerError};
+use lox::token::TokenType;
+use lox::VM;
+
+fn tokens(input: &str) -> Vec<TokenType<'_>> {
+    Lexer::new(input, Reporter::new())
+        .lex()
+        .into_iter()
+        .map(|token| token.value.ty)
+        .collect()
+}
+
+#[test]
+fn block_comments_nest() {
+    assert_eq!(
+        tokens("1 /* a /* b */ c */ 2 /**/ 3"),
+        [
+            TokenType::Number(1.0),
+            TokenType::Number(2.0),
+            TokenType::Number(3.0),
+            TokenType::EOF
+        ]
+    );
+    assert_eq!(tokens("a/b/* x */"), tokens("a / b"));
+}
+
+#[test]
+fn unclosed_block_comments_are_errors() {
+    let found = tokens("1; /* a /* b */");
+
+    assert_eq!(
+        found[1..],
+        [
+            TokenType::Semicolon,
+            TokenType::Error(LexerError::UnclosedBlockComment),
+            TokenType::EOF
+        ]
+    );
+}
+
+#[test]
+fn comments_are_kept_for_tooling() {
+    let found = Lexer::new("// a\n/// b\n/* c */ 1", Reporter::new())
+        .keep_comments()
+        .lex()
+        .into_iter()
+        .map(|token| token.value.ty)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        found,
+        [
+            TokenType::Comment,
+            TokenType::DocComment(" b"),
+            TokenType::Comment,
+            TokenType::Number(1.0),
+            TokenType::EOF
+        ]
+    );
+}
+
+#[test]
+fn doc_comments_are_attached_to_the_next_token() {
+    let tokens = Lexer::new(
+        "/// The answer\n// not docs\n///  to everything\nvar a;",
+        Reporter::new(),
+    )
+    .lex();
+
+    assert_eq!(tokens[0].value.ty, TokenType::Var);
+    assert_eq!(tokens[0].value.doc, [" The answer", "  to everything"]);
+    assert!(tokens[1].value.doc.is_empty());
+}
+
+#[test]
+fn doc_comments_document_globals() {
+    let mut vm = VM::new();
+
+    vm.eval("/// The answer\n/// to everything\nvar answer = 42;\n//// Not docs\nvar other;")
+        .unwrap();
+
+    assert_eq!(vm.doc("answer"), Some("The answer\nto everything"));
+    assert_eq!(vm.doc("other"), None);
+    assert_eq!(vm.doc("missing"), None);
+}
@@ -0,0 +1,53 @@
+extern crate lox;
+
+use lox::VM;
+
+#[test]
+fn long_scripts_fit_in_the_constants() {
+    let mut input = String::from("var a = 1;\nvar b = 2;\n");
+
+    for _ in 0..300 {
+        input.push_str("a + b;\n");
+    }
+
+    input.push_str("a + b + a;");
+
+    let mut vm = VM::new();
+
+    assert_eq!(vm.eval(&input).unwrap().try_number(), Some(4.0));
+}
+
+#[test]
+fn repeated_constants_are_stored_once() {
+    let mut vm = VM::new();
+
+    let listing = vm
+        .disassemble("test.lox", "var b = a;\na;\n\"a\" + \"b\";\n1.5; 1.5;")
+        .unwrap();
+
+    let expected = "\
+== test.lox ==
+0000    1 GET_GLOBAL          1 'a'
+0002    | DEFINE_GLOBAL       0 'b'
+0004    2 GET_GLOBAL          1 'a'
+0006    | POP
+0007    3 CONSTANT            1 'a'
+0009    | CONSTANT            0 'b'
+0011    | ADD
+0012    | POP
+0013    4 CONSTANT            2 '1.5'
+0015    | POP
+0016    | CONSTANT            2 '1.5'
+0018    | RETURN
+";
+
+    assert_eq!(listing, expected);
+}
+
+#[test]
+fn repeated_literals_fit_in_the_constants() {
+    let mut vm = VM::new();
+
+    assert_eq!(vm.eval(&"1;".repeat(300)).unwrap().try_number(), Some(1.0));
+    assert_eq!(vm.eval(&"\"s\";".repeat(300)).unwrap().try_str(), Some("s"));
+}
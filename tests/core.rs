@@ -0,0 +1,69 @@
+extern crate lox;
+
+use lox::{Error, VM};
+
+fn eval(input: &str) -> String {
+    VM::new().eval(input).unwrap().to_string()
+}
+
+fn runtime_error(input: &str) -> String {
+    match VM::new().eval(input) {
+        Err(Error::Runtime(e)) => e.msg,
+        other => panic!("Expected a runtime error, got {:?}", other),
+    }
+}
+
+#[test]
+fn type_names_every_type() {
+    assert_eq!(
+        eval("[type(nil), type(true), type(1), type(\"a\"), type([]), type({}), type(type), type(Math)];"),
+        "[\"nil\", \"bool\", \"number\", \"string\", \"list\", \"map\", \"native\", \"module\"]"
+    );
+}
+
+#[test]
+fn str_and_num_convert() {
+    assert_eq!(eval("str(1.5) + str(nil) + str(\"a\");"), "1.5nila");
+    assert_eq!(eval("num(\" 2.5 \") + num(1);"), "3.5");
+    assert_eq!(
+        runtime_error("num(\"two\");"),
+        "Could not convert \"two\" to a number."
+    );
+
+    for input in &["inf", "-inf", "NaN", "1e40"] {
+        assert_eq!(
+            runtime_error(&format!("num(\"{}\");", input)),
+            format!("Could not convert \"{}\" to a number.", input)
+        );
+    }
+    assert_eq!(
+        runtime_error("num(nil);"),
+        "`num` expected a string but got `nil`."
+    );
+}
+
+#[test]
+fn len_counts_chars_and_items() {
+    assert_eq!(
+        eval("[len(\"héllo\"), len([1, 2]), len({1: 2})];"),
+        "[5, 2, 1]"
+    );
+    assert_eq!(runtime_error("len(1);"), "`1` has no length.");
+}
+
+#[test]
+fn assert_fails_with_its_message() {
+    assert_eq!(eval("assert(1 == 1, \"math\");"), "nil");
+    assert_eq!(
+        runtime_error("assert(1 == 2, \"math\");"),
+        "Assertion failed: math"
+    );
+}
+
+#[test]
+fn clock_counts_up() {
+    assert_eq!(
+        eval("var a = clock();\n[a >= 0, clock() >= a];"),
+        "[true, true]"
+    );
+}
@@ -0,0 +1,203 @@
+extern crate lox;
+
+use lox::error::{self, ColorChoice, Level};
+use lox::{Error, VM};
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+const COMPARISON: &str = "var a = 1;\na = 2;\n";
+
+/// Writes a script to a directory of its own, returning its path
+fn script(name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lox-diagnostics-{}-{}", std::process::id(), name));
+    fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join("main.lox");
+    fs::write(&path, contents).unwrap();
+
+    path
+}
+
+/// Runs the interpreter on a script from the directory the script is in
+fn lox(args: &[&str], name: &str, contents: &str) -> Output {
+    let path = script(name, contents);
+    let dir = path.parent().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_lox"))
+        .args(args)
+        .arg("main.lox")
+        .current_dir(dir)
+        .output()
+        .unwrap();
+
+    fs::remove_dir_all(dir).unwrap();
+    output
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn warnings_carry_help_and_suggestions() {
+    let mut vm = VM::new();
+
+    assert_eq!(vm.eval(COMPARISON).unwrap().try_bool(), Some(false));
+
+    let diagnostics = vm.diagnostics().diagnostics();
+
+    assert_eq!(diagnostics.len(), 1);
+
+    let warning = &diagnostics[0];
+
+    assert_eq!(warning.level(), Level::Warning);
+    assert_eq!(warning.error_code(), Some("W0001"));
+    assert_eq!(warning.msg(), "`=` compares the values on either side");
+    assert_eq!(warning.suggestions()[0].replacement, "==");
+    assert_eq!(warning.suggestions()[0].msg, "did you mean `==`?");
+}
+
+#[test]
+fn denied_warnings_are_errors() {
+    let mut vm = VM::new();
+
+    vm.deny_warnings(true);
+
+    let reporter = match vm.eval(COMPARISON) {
+        Err(Error::Compile(reporter)) => reporter,
+        other => panic!("Expected a compile error, got {:?}", other),
+    };
+
+    let diagnostics = reporter.diagnostics();
+
+    assert_eq!(diagnostics[0].level(), Level::Error);
+    assert_eq!(
+        diagnostics[0].children(),
+        [(Level::Note, "warnings are denied".to_string())]
+    );
+}
+
+#[test]
+fn prints_warnings_with_a_snippet() {
+    let mut vm = VM::new();
+
+    vm.eval_file("main.lox", COMPARISON).unwrap();
+
+    let mut out = vec![];
+    let warning = &vm.diagnostics().diagnostics()[0];
+
+    error::print(&mut out, vm.source_map(), warning, false).unwrap();
+
+    let expected = "\
+warning[W0001]: `=` compares the values on either side
+  --> main.lox:2:3
+   1 | var a = 1;
+   2 | a = 2;
+     |   ^
+help: did you mean `==`?
+   2 | a == 2;
+";
+
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+}
+
+#[test]
+fn deny_warnings_fails_the_run() {
+    let allowed = lox(&["--color=never"], "allowed", COMPARISON);
+
+    assert_eq!(allowed.status.code(), Some(0));
+    assert!(stderr(&allowed).starts_with("warning[W0001]"));
+
+    let denied = lox(&["--deny-warnings", "--color=never"], "denied", COMPARISON);
+
+    assert_eq!(denied.status.code(), Some(65));
+    assert!(stderr(&denied).starts_with("error[W0001]"));
+    assert!(stderr(&denied).contains("= note: warnings are denied"));
+}
+
+#[test]
+fn colors_are_chosen_by_flag_or_terminal() {
+    let always = lox(&["--color=always"], "always", COMPARISON);
+    let never = lox(&["--color=never"], "never", COMPARISON);
+    // The tests capture stderr, so it isn't a terminal
+    let auto = lox(&[], "auto", COMPARISON);
+
+    assert!(stderr(&always).starts_with("\x1b[1;33mwarning[W0001]\x1b[0m"));
+    assert!(!stderr(&never).contains('\x1b'));
+    assert_eq!(stderr(&auto), stderr(&never));
+
+    let invalid = lox(&["--color=sometimes"], "invalid", COMPARISON);
+
+    assert_eq!(invalid.status.code(), Some(64));
+    assert!(stderr(&invalid)
+        .starts_with("Unknown color choice `sometimes`, expected `always`, `never` or `auto`"));
+}
+
+#[test]
+fn colors_mark_the_level_and_snippet() {
+    let mut vm = VM::new();
+
+    vm.eval_file("main.lox", COMPARISON).unwrap();
+
+    let mut out = vec![];
+    let warning = &vm.diagnostics().diagnostics()[0];
+
+    error::print(&mut out, vm.source_map(), warning, true).unwrap();
+
+    let out = String::from_utf8(out).unwrap();
+
+    assert!(
+        out.contains("\x1b[1;34m-->\x1b[0m main.lox:2:3"),
+        "{:?}",
+        out
+    );
+    assert!(ColorChoice::Always.enabled());
+    assert!(!ColorChoice::Never.enabled());
+    assert_eq!("auto".parse(), Ok(ColorChoice::Auto));
+}
+
+#[test]
+fn diagnostics_serialize_to_json() {
+    let mut vm = VM::new();
+
+    vm.eval_file("main.lox", COMPARISON).unwrap();
+
+    let warning = &vm.diagnostics().diagnostics()[0];
+    let span = r#"{"start":{"line":2,"column":3,"absolute":13},"end":{"line":2,"column":4,"absolute":14}}"#;
+
+    assert_eq!(
+        warning.to_json(Some("main.lox")),
+        format!(
+            concat!(
+                r#"{{"level":"warning","code":"W0001","message":"`=` compares the values on either side","#,
+                r#""file":"main.lox","span":{0},"labels":[],"children":[],"#,
+                r#""suggestions":[{{"message":"did you mean `==`?","replacement":"==","span":{0}}}]}}"#
+            ),
+            span
+        )
+    );
+    assert!(warning
+        .to_json(Some("dir\\\"quoted\"\n.lox"))
+        .contains(r#""file":"dir\\\"quoted\"\n.lox""#));
+    assert!(warning.to_json(None).contains(r#""file":null"#));
+}
+
+#[test]
+fn json_format_prints_a_line_per_diagnostic() {
+    let output = lox(
+        &["--error-format=json"],
+        "json",
+        "var a = 1;\na = 2;\nvar = 3;\n",
+    );
+    let stderr = stderr(&output);
+    let lines = stderr.lines().collect::<Vec<_>>();
+
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(lines.len(), 2, "{}", stderr);
+    assert!(lines[0].starts_with(r#"{"level":"warning","code":"W0001""#));
+    assert!(lines[1].starts_with(r#"{"level":"error","code":"E0"#));
+    assert!(lines
+        .iter()
+        .all(|line| line.ends_with('}') && line.contains(r#""file":"main.lox""#)));
+}
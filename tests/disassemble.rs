@@ -0,0 +1,185 @@
+extern crate lox;
+
+use lox::chunks::Chunk;
+use lox::op::opcode;
+use lox::{Value, VM};
+use std::io::Write;
+
+fn disassemble(input: &str) -> String {
+    VM::new().disassemble("test.lox", input).unwrap()
+}
+
+#[test]
+fn arithmetic_and_comparisons() {
+    let input = "\
+         var a = -1 + 2 * 3 - 4 / 5;\n\
+         !(a == 1) != true;\n\
+         a > 1; a >= 1; a < nil; a <= false;\n";
+
+    let expected = "\
+== test.lox ==
+0000    1 CONSTANT            1 '1'
+0002    | NEGATE
+0003    | CONSTANT            2 '2'
+0005    | CONSTANT            3 '3'
+0007    | MUL
+0008    | ADD
+0009    | CONSTANT            4 '4'
+0011    | CONSTANT            5 '5'
+0013    | DIV
+0014    | SUB
+0015    | DEFINE_GLOBAL       0 'a'
+0017    2 GET_GLOBAL          0 'a'
+0019    | CONSTANT            1 '1'
+0021    | EQUAL
+0022    | NOT
+0023    | TRUE
+0024    | EQUAL
+0025    | NOT
+0026    | POP
+0027    3 GET_GLOBAL          0 'a'
+0029    | CONSTANT            1 '1'
+0031    | GREATER
+0032    | POP
+0033    | GET_GLOBAL          0 'a'
+0035    | CONSTANT            1 '1'
+0037    | LESS
+0038    | NOT
+0039    | POP
+0040    | GET_GLOBAL          0 'a'
+0042    | NIL
+0043    | LESS
+0044    | POP
+0045    | GET_GLOBAL          0 'a'
+0047    | FALSE
+0048    | GREATER
+0049    | NOT
+0050    | RETURN
+";
+
+    assert_eq!(disassemble(input), expected);
+}
+
+#[test]
+fn collections_and_methods() {
+    let input = "\
+         var l = [1, \"two\\n\"];\n\
+         l[0] = l[1];\n\
+         var m = {\"k\": l};\n\
+         m.len();\n\
+         \"${l.len()} items\";\n";
+
+    let expected = "\
+== test.lox ==
+0000    1 CONSTANT            1 '1'
+0002    | CONSTANT            2 'two\\n'
+0004    | BUILD_LIST          2
+0006    | DEFINE_GLOBAL       0 'l'
+0008    2 GET_GLOBAL          0 'l'
+0010    | CONSTANT            3 '0'
+0012    | GET_GLOBAL          0 'l'
+0014    | CONSTANT            1 '1'
+0016    | INDEX_GET
+0017    | INDEX_SET
+0018    | POP
+0019    3 CONSTANT            5 'k'
+0021    | GET_GLOBAL          0 'l'
+0023    | BUILD_MAP           1
+0025    | DEFINE_GLOBAL       4 'm'
+0027    4 GET_GLOBAL          4 'm'
+0029    | INVOKE              6 'len'    0
+0032    | POP
+0033    5 CONSTANT            7 ''
+0035    | GET_GLOBAL          0 'l'
+0037    | INVOKE              6 'len'    0
+0040    | TO_STRING
+0041    | ADD
+0042    | CONSTANT            8 ' items'
+0044    | ADD
+0045    | RETURN
+";
+
+    assert_eq!(disassemble(input), expected);
+}
+
+#[test]
+fn imports_and_calls() {
+    let input = "\
+         import \"lib/util.lox\";\n\
+         import { a, b } from \"other.lox\";\n\
+         clock();\n\
+         Math.pi;\n";
+
+    let expected = "\
+== test.lox ==
+0000    1 IMPORT              0 'lib/util.lox'
+0002    | DEFINE_GLOBAL       1 'util'
+0004    2 IMPORT              2 'other.lox'
+0006    | GET_PROPERTY        3 'a'
+0008    | DEFINE_GLOBAL       3 'a'
+0010    | IMPORT              2 'other.lox'
+0012    | GET_PROPERTY        4 'b'
+0014    | DEFINE_GLOBAL       4 'b'
+0016    3 GET_GLOBAL          5 'clock'
+0018    | CALL                0
+0020    | POP
+0021    4 GET_GLOBAL          6 'Math'
+0023    | GET_PROPERTY        7 'pi'
+0025    | RETURN
+";
+
+    assert_eq!(disassemble(input), expected);
+}
+
+#[test]
+fn code_is_on_the_line_of_its_first_token() {
+    let expected = "\
+== test.lox ==
+0000    1 CONSTANT            1 '1'
+0002    | DEFINE_GLOBAL       0 'a'
+0004    2 GET_GLOBAL          2 'b'
+0006    | RETURN
+";
+
+    assert_eq!(disassemble("var a = 1;\nb;\n"), expected);
+
+    match VM::new().eval("var a = 1;\nb;") {
+        Err(lox::Error::Runtime(e)) => assert_eq!(e.line, 2),
+        other => panic!("Expected a runtime error, got {:?}", other),
+    }
+}
+
+#[test]
+fn malformed_chunks() {
+    let mut chunk = Chunk::new();
+    chunk.add_constant(Value::number(1.0));
+
+    // A constant out of bounds, an unknown opcode and a truncated constant
+    for &byte in &[0x01, 0x05, 0xff, 0x01] {
+        chunk.write(byte, 1);
+    }
+
+    let expected = "\
+== bad ==
+0000    1 CONSTANT            5 <invalid constant>
+0002    | UNKNOWN 0xff
+0003    | CONSTANT         <missing operand>
+";
+
+    assert_eq!(chunk.disassemble("bad").to_string(), expected);
+}
+
+#[test]
+fn writes_to_io() {
+    let mut chunk = Chunk::new();
+    let mut out = Vec::new();
+
+    chunk.write(opcode::NIL, 1);
+    chunk.write(opcode::RETURN, 1);
+    write!(out, "{}", chunk.disassemble("io")).unwrap();
+
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "== io ==\n0000    1 NIL\n0001    | RETURN\n"
+    );
+}
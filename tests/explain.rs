@@ -0,0 +1,79 @@
+extern crate lox;
+
+use std::process::{Command, Output};
+
+use lox::codes;
+
+const CODES: &[&str] = &[
+    codes::UNCLOSED_STRING,
+    codes::UNCLOSED_BLOCK_COMMENT,
+    codes::UNCLOSED_INTERPOLATION,
+    codes::EMPTY_INTERPOLATION,
+    codes::UNKNOWN_ESCAPE,
+    codes::INVALID_UNICODE_ESCAPE,
+    codes::INVALID_DIGIT,
+    codes::INVALID_SEPARATOR,
+    codes::MISSING_DIGITS,
+    codes::MISSING_EXPONENT,
+    codes::NUMBER_TOO_LARGE,
+    codes::UNEXPECTED_CHAR,
+    codes::UNEXPECTED_EOF,
+    codes::EXPECTED_EXPRESSION,
+    codes::EXPECTED_TOKEN,
+    codes::EXPECTED_NAME,
+    codes::TOO_MANY_CONSTANTS,
+    codes::TOO_MANY_ITEMS,
+    codes::MODULE_NAME,
+    codes::EQUAL_COMPARISON,
+];
+
+fn lox(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lox"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn every_code_is_explained() {
+    for code in CODES {
+        let explanation = codes::explain(code).unwrap_or_else(|| panic!("{} is unexplained", code));
+
+        assert!(!explanation.is_empty(), "{}", code);
+    }
+}
+
+#[test]
+fn codes_are_case_insensitive() {
+    assert_eq!(codes::explain("e0001"), codes::explain("E0001"));
+    assert_eq!(codes::explain("E9999"), None);
+    assert_eq!(codes::explain(""), None);
+}
+
+#[test]
+fn explain_prints_the_explanation() {
+    let output = lox(&["--explain", "E0001"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout.starts_with("A string literal was not closed before the end of the input."));
+    assert_eq!(stdout.trim_end(), codes::explain("E0001").unwrap());
+}
+
+#[test]
+fn explain_rejects_unknown_codes() {
+    let unknown = lox(&["--explain", "E9999"]);
+
+    assert_eq!(unknown.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(unknown.stderr).unwrap(),
+        "`E9999` is not a known error code\n"
+    );
+
+    let missing = lox(&["--explain"]);
+
+    assert_eq!(missing.status.code(), Some(64));
+    assert!(String::from_utf8(missing.stderr)
+        .unwrap()
+        .starts_with("`--explain` expects an error code such as E0001"));
+}
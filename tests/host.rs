@@ -0,0 +1,83 @@
+extern crate lox;
+
+use lox::{HostValue, VM};
+
+#[test]
+fn values_outlive_the_vm() {
+    let value = {
+        let mut vm = VM::new();
+        vm.eval("var s = \"hello\".upper();").unwrap();
+        vm.eval("s + \"!\";").unwrap()
+    };
+
+    assert_eq!(value.try_str(), Some("HELLO!"));
+}
+
+#[test]
+fn values_survive_later_evaluations() {
+    let mut vm = VM::new();
+    let list = vm.eval("[\"a\" + \"b\", 1];").unwrap();
+
+    for _ in 0..100 {
+        vm.eval("var garbage = [\"x\" + \"y\"];").unwrap();
+    }
+
+    assert_eq!(
+        list,
+        HostValue::List(vec![HostValue::String("ab".into()), HostValue::Number(1.0)])
+    );
+    assert_eq!(list.to_string(), "[\"ab\", 1]");
+}
+
+#[test]
+fn cycles_are_copied_once() {
+    let mut vm = VM::new();
+    let list = vm.eval("var xs = [1];\nxs.push(xs);\nxs;").unwrap();
+
+    assert_eq!(
+        list,
+        HostValue::List(vec![HostValue::Number(1.0), HostValue::ListCycle])
+    );
+    assert_eq!(list.to_string(), "[1, [...]]");
+    assert_eq!(vm.get_global("xs"), Some(list));
+
+    let map = vm.eval("var m = {};\nm[\"m\"] = m;\nm;").unwrap();
+
+    assert_eq!(
+        map,
+        HostValue::Map(vec![(HostValue::String("m".into()), HostValue::MapCycle)])
+    );
+    assert_eq!(map.to_string(), "{\"m\": {...}}");
+}
+
+#[test]
+fn globals_are_shared_with_the_host() {
+    let mut vm = lox::Lox::new();
+
+    vm.set_global("answer", lox::Value::number(41.0));
+    vm.eval("var result = answer + 1;").unwrap();
+
+    assert_eq!(vm.get_global("result"), Some(HostValue::Number(42.0)));
+    assert_eq!(vm.get_global("missing"), None);
+    assert!(vm.eval("var x = 1;").unwrap().is_nil());
+}
+
+#[test]
+fn errors_are_returned_to_the_host() {
+    let mut vm = VM::new();
+
+    match vm.eval("1 +;") {
+        Err(lox::Error::Compile(reporter)) => assert!(reporter.has_error()),
+        other => panic!("Expected a compile error, got {:?}", other),
+    }
+
+    match vm.eval("-\"a\";") {
+        Err(lox::Error::Runtime(e)) => {
+            assert_eq!(e.msg, "Unary `-` operand must be a number.")
+        }
+        other => panic!("Expected a runtime error, got {:?}", other),
+    }
+
+    // The vm can still be used after errors
+    assert_eq!(vm.eval("1 + 2;").unwrap(), HostValue::Number(3.0));
+}
@@ -0,0 +1,248 @@
+extern crate lox;
+
+use lox::{Error, HostValue, VM};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A directory of modules, removed when dropped
+struct Modules(PathBuf);
+
+impl Modules {
+    fn new(name: &str, files: &[(&str, &str)]) -> Self {
+        let dir = std::env::temp_dir().join(format!("lox-imports-{}-{}", std::process::id(), name));
+
+        for &(file, contents) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        Modules(dir)
+    }
+
+    fn path(&self, file: &str) -> PathBuf {
+        self.0.join(file)
+    }
+
+    /// Evaluates `input` as if it was the file `main.lox` in the directory
+    fn eval(&self, vm: &mut VM, input: &str) -> Result<HostValue, Error> {
+        vm.eval_file(self.path("main.lox").to_str().unwrap(), input)
+    }
+}
+
+impl Drop for Modules {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn runtime_error(result: Result<HostValue, Error>) -> String {
+    match result {
+        Err(Error::Runtime(e)) => e.msg,
+        other => panic!("Expected a runtime error, got {:?}", other),
+    }
+}
+
+#[test]
+fn imports_a_module_as_a_global() {
+    let modules = Modules::new(
+        "global",
+        &[(
+            "lib/util.lox",
+            "var twice = 2 * 21;\nvar name = \"util\";\n",
+        )],
+    );
+    let mut vm = VM::new();
+
+    let value = modules
+        .eval(&mut vm, "import \"lib/util.lox\";\nutil.twice;")
+        .unwrap();
+
+    assert_eq!(value, HostValue::Number(42.0));
+    assert_eq!(
+        vm.get_global("util"),
+        Some(HostValue::Module("util".into()))
+    );
+    assert_eq!(
+        modules.eval(&mut vm, "util.name;").unwrap(),
+        HostValue::String("util".into())
+    );
+}
+
+#[test]
+fn imports_names_from_a_module() {
+    let modules = Modules::new(
+        "names",
+        &[("lib.lox", "var a = 1;\nvar b = 2;\nvar c = 3;\n")],
+    );
+    let mut vm = VM::new();
+
+    let value = modules
+        .eval(&mut vm, "import { a, b, } from \"lib.lox\";\na + b;")
+        .unwrap();
+
+    assert_eq!(value, HostValue::Number(3.0));
+    assert_eq!(vm.get_global("c"), None);
+    assert_eq!(vm.get_global("lib"), None);
+}
+
+#[test]
+fn finds_modules_in_search_paths() {
+    let modules = Modules::new("search", &[("vendor/shapes.lox", "var sides = 4;\n")]);
+    let mut vm = VM::new();
+
+    assert_eq!(
+        runtime_error(modules.eval(&mut vm, "import \"shapes.lox\";")),
+        "Cannot find module `shapes.lox`."
+    );
+
+    vm.add_search_path(modules.path("vendor"));
+
+    let value = modules
+        .eval(&mut vm, "import \"shapes.lox\";\nshapes.sides;")
+        .unwrap();
+
+    assert_eq!(value, HostValue::Number(4.0));
+}
+
+#[test]
+fn modules_run_once() {
+    let modules = Modules::new("once", &[("cached.lox", "var items = [];\n")]);
+    let mut vm = VM::new();
+
+    // Lists are only equal to themselves, so a module that ran twice would have two lists
+    assert_eq!(
+        modules.eval(&mut vm, "[] == [];").unwrap(),
+        HostValue::Bool(false)
+    );
+
+    let value = modules
+        .eval(
+            &mut vm,
+            "import \"cached.lox\";\nimport { items } from \"./cached.lox\";\ncached.items == items;",
+        )
+        .unwrap();
+
+    assert_eq!(value, HostValue::Bool(true));
+}
+
+#[test]
+fn modules_export_only_their_own_globals() {
+    let modules = Modules::new("exports", &[("lib.lox", "var own = clock;\n")]);
+    let mut vm = VM::new();
+
+    let value = modules.eval(&mut vm, "import \"lib.lox\";\nlib;").unwrap();
+
+    assert_eq!(value, HostValue::Module("lib".into()));
+    assert_eq!(
+        runtime_error(modules.eval(&mut vm, "lib.clock;")),
+        "Module `lib` has no property `clock`."
+    );
+    assert_eq!(
+        modules.eval(&mut vm, "lib.own;").unwrap(),
+        HostValue::Native("clock".into())
+    );
+}
+
+#[test]
+fn modules_can_export_prelude_globals() {
+    let modules = Modules::new("reexports", &[("lib/same.lox", "var len = len;\n")]);
+    let mut vm = VM::new();
+
+    let value = modules
+        .eval(
+            &mut vm,
+            "import { len } from \"lib/same.lox\";\nlen(\"abc\");",
+        )
+        .unwrap();
+
+    assert_eq!(value, HostValue::Number(3.0));
+}
+
+#[test]
+fn modules_have_their_own_globals() {
+    let modules = Modules::new("globals", &[("lib.lox", "var seen = secret;\n")]);
+    let mut vm = VM::new();
+
+    let error = runtime_error(modules.eval(&mut vm, "var secret = 1;\nimport \"lib.lox\";"));
+
+    assert_eq!(error, "Undefined variable `secret`.");
+}
+
+#[test]
+fn cyclic_imports_are_errors() {
+    let modules = Modules::new(
+        "cycle",
+        &[
+            ("a.lox", "import \"b.lox\";\n"),
+            ("b.lox", "import \"a.lox\";\n"),
+        ],
+    );
+    let mut vm = VM::new();
+    let a = modules.path("a.lox");
+    let b = modules.path("b.lox");
+
+    let error = runtime_error(vm.eval_file(a.to_str().unwrap(), &fs::read_to_string(&a).unwrap()));
+
+    assert_eq!(
+        error,
+        format!(
+            "Cyclic import: `{}` imports `{}` imports `{}`.",
+            a.display(),
+            b.display(),
+            a.display()
+        )
+    );
+}
+
+#[test]
+fn module_errors_are_runtime_errors() {
+    let modules = Modules::new(
+        "errors",
+        &[
+            ("broken.lox", "var = 1;\n"),
+            ("bad.lox", "var x = 1;\nvar y = -\"x\";\n"),
+        ],
+    );
+    let mut vm = VM::new();
+
+    // The module's own diagnostics explain why it didn't compile
+    match modules.eval(&mut vm, "import \"broken.lox\";") {
+        Err(Error::Compile(reporter)) => assert_eq!(
+            reporter.diagnostics()[0].msg(),
+            "Expected a variable name instead found `=`"
+        ),
+        other => panic!("Expected a compile error, got {:?}", other),
+    }
+
+    let error = match modules.eval(&mut vm, "import \"bad.lox\";") {
+        Err(Error::Runtime(e)) => e,
+        other => panic!("Expected a runtime error, got {:?}", other),
+    };
+
+    assert_eq!(Path::new(&error.path), modules.path("bad.lox"));
+    assert_eq!(error.line, 2);
+}
+
+#[test]
+fn module_names_must_be_identifiers() {
+    let modules = Modules::new("identifiers", &[("my-lib.lox", "var a = 1;\n")]);
+    let mut vm = VM::new();
+
+    assert!(modules.eval(&mut vm, "import \"my-lib.lox\";").is_err());
+
+    let diagnostics = vm.diagnostics().diagnostics();
+    let error = &diagnostics[0];
+
+    assert_eq!(error.error_code(), Some("E0019"));
+    assert_eq!(
+        error.msg(),
+        "Cannot name a global after the module `my-lib.lox`"
+    );
+    assert_eq!(
+        modules
+            .eval(&mut vm, "import { a } from \"my-lib.lox\";\na;")
+            .unwrap(),
+        HostValue::Number(1.0)
+    );
+}
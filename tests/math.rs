@@ -0,0 +1,54 @@
+extern crate lox;
+
+use lox::{Error, HostValue, VM};
+
+fn number(vm: &mut VM, input: &str) -> f32 {
+    vm.eval(input).unwrap().try_number().unwrap()
+}
+
+#[test]
+fn defines_constants_and_functions() {
+    let mut vm = VM::new();
+
+    assert_eq!(number(&mut vm, "Math.PI;"), std::f32::consts::PI);
+    assert_eq!(vm.eval("Math;").unwrap(), HostValue::Module("Math".into()));
+    assert_eq!(number(&mut vm, "Math.sqrt(16);"), 4.0);
+    assert_eq!(number(&mut vm, "Math.floor(-1.5) + Math.ceil(1.2);"), 0.0);
+    assert_eq!(number(&mut vm, "Math.pow(2, 10);"), 1024.0);
+    assert_eq!(number(&mut vm, "Math.max(1, Math.min(5, 3));"), 3.0);
+    assert_eq!(number(&mut vm, "Math.abs(-2) * Math.round(2.5);"), 6.0);
+}
+
+#[test]
+fn checks_arguments() {
+    let mut vm = VM::new();
+
+    match vm.eval("Math.sqrt(\"4\");") {
+        Err(Error::Runtime(e)) => assert_eq!(e.msg, "`Math.sqrt` expected a number but got `4`."),
+        other => panic!("Expected a runtime error, got {:?}", other),
+    }
+
+    match vm.eval("Math.nope;") {
+        Err(Error::Runtime(e)) => assert_eq!(e.msg, "Module `Math` has no property `nope`."),
+        other => panic!("Expected a runtime error, got {:?}", other),
+    }
+}
+
+#[test]
+fn random_numbers_repeat_for_a_seed() {
+    let mut vm = VM::new();
+    let input = "Math.seed(42);\n[Math.random(), Math.random(), Math.random()];";
+
+    let first = vm.eval(input).unwrap();
+    let second = VM::new().eval(input).unwrap();
+
+    assert_eq!(first, second);
+
+    for _ in 0..1000 {
+        let random = number(&mut vm, "Math.random();");
+        assert!((0.0..1.0).contains(&random), "{}", random);
+    }
+
+    let other = vm.eval("Math.seed(7);\n[Math.random(), Math.random(), Math.random()];");
+    assert_ne!(other.unwrap(), first);
+}
@@ -0,0 +1,106 @@
+extern crate lox;
+
+use lox::{Error, RuntimeError, Value, VM};
+
+#[test]
+fn natives_can_evaluate_input() {
+    let mut vm = VM::new();
+
+    vm.define_native("inner", 0, |vm, _| {
+        let value = vm
+            .eval("1 + 1;")
+            .map_err(|e| RuntimeError::new(e.to_string()))?;
+
+        Ok(Value::number(value.try_number().unwrap()))
+    });
+
+    let value = vm.eval("var a = inner();\nvar b = 10;\nb + a;").unwrap();
+
+    assert_eq!(value.try_number(), Some(12.0));
+}
+
+#[test]
+fn errors_in_evaluated_input_leave_the_caller_running() {
+    let mut vm = VM::new();
+
+    vm.define_native("attempt", 1, |vm, args| {
+        let input = vm.to_host(args[0]).to_string();
+
+        Ok(Value::bool(vm.eval(&input).is_ok()))
+    });
+
+    let value = vm
+        .eval("var x = 1;\n[x, attempt(\"missing;\"), attempt(\"x + 1;\"), x + 1];")
+        .unwrap();
+
+    assert_eq!(value.to_string(), "[1, false, true, 2]");
+}
+
+#[test]
+fn evaluated_input_cannot_overflow_the_caller_stack() {
+    let mut vm = VM::new();
+
+    vm.define_native("deep", 0, |vm, _| match vm.eval("1;") {
+        Ok(_) => Ok(Value::nil()),
+        Err(Error::Runtime(e)) => Err(e),
+        Err(e) => Err(RuntimeError::new(e.to_string())),
+    });
+
+    let items = vec!["1"; 254].join(", ");
+    let input = format!("[{}, deep()];", items);
+
+    match vm.eval(&input) {
+        Err(Error::Runtime(e)) => assert_eq!(e.msg, "Stack overflow."),
+        other => panic!("Expected a stack overflow, got {:?}", other),
+    }
+
+    assert!(vm.eval("deep();").unwrap().is_nil());
+}
+
+fn runtime_error(vm: &mut VM, input: &str) -> String {
+    match vm.eval(input) {
+        Err(Error::Runtime(e)) => e.msg,
+        other => panic!("Expected a runtime error, got {:?}", other),
+    }
+}
+
+#[test]
+fn natives_receive_their_arguments() {
+    let mut vm = VM::new();
+
+    vm.define_native("add", 2, |_, args| {
+        Ok(Value::number(args[0].as_number() + args[1].as_number()))
+    });
+
+    assert_eq!(
+        vm.eval("add(1, add(2, 3));").unwrap().try_number(),
+        Some(6.0)
+    );
+    assert_eq!(vm.eval("add;").unwrap().to_string(), "<native fn add>");
+}
+
+#[test]
+fn natives_check_their_arity() {
+    let mut vm = VM::new();
+
+    vm.define_native("one", 1, |_, args| Ok(args[0]));
+
+    assert_eq!(
+        runtime_error(&mut vm, "one();"),
+        "`one` expected 1 arguments but got 0."
+    );
+    assert_eq!(
+        runtime_error(&mut vm, "one(1, 2);"),
+        "`one` expected 1 arguments but got 2."
+    );
+    assert_eq!(runtime_error(&mut vm, "1();"), "Can only call functions.");
+}
+
+#[test]
+fn natives_raise_errors() {
+    let mut vm = VM::new();
+
+    vm.define_native("fail", 0, |_, _| Err(RuntimeError::new("Failed.")));
+
+    assert_eq!(runtime_error(&mut vm, "fail();"), "Failed.");
+}
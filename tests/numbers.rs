@@ -0,0 +1,73 @@
+extern crate lox;
+
+use lox::error::Reporter;
+use lox::scanner::{Lexer, LexerError};
+use lox::token::TokenType;
+
+fn tokens(input: &str) -> Vec<TokenType<'_>> {
+    Lexer::new(input, Reporter::new())
+        .lex()
+        .into_iter()
+        .map(|token| token.value.ty)
+        .collect()
+}
+
+fn number(input: &str) -> TokenType<'_> {
+    tokens(input).remove(0)
+}
+
+#[test]
+fn lexes_decimals() {
+    assert_eq!(number("1_000.2_5"), TokenType::Number(1000.25));
+    assert_eq!(number("1.5e3"), TokenType::Number(1500.0));
+    assert_eq!(number("2E-2"), TokenType::Number(0.02));
+    assert_eq!(number("3.4e38"), TokenType::Number(3.4e38));
+}
+
+#[test]
+fn lexes_prefixed_integers() {
+    assert_eq!(number("0xff"), TokenType::Number(255.0));
+    assert_eq!(number("0b1010_1010"), TokenType::Number(170.0));
+    assert_eq!(number("0o17"), TokenType::Number(15.0));
+}
+
+#[test]
+fn rejects_numbers_too_large_for_a_float() {
+    let error = TokenType::Error(LexerError::NumberTooLarge);
+
+    assert_eq!(number("1e40"), error);
+    assert_eq!(number("0x1_0000_0000_0000_0000"), error);
+}
+
+#[test]
+fn rejects_misplaced_separators() {
+    let error = TokenType::Error(LexerError::InvalidSeparator);
+
+    assert_eq!(number("1__0"), error);
+    assert_eq!(number("10_"), error);
+    assert_eq!(number("1._5"), error);
+    assert_eq!(number("1e_5"), error);
+}
+
+#[test]
+fn rejects_invalid_digits_and_exponents() {
+    assert_eq!(
+        number("0b12"),
+        TokenType::Error(LexerError::InvalidDigit('2', 2))
+    );
+    assert_eq!(number("0x"), TokenType::Error(LexerError::MissingDigits));
+    assert_eq!(number("1e"), TokenType::Error(LexerError::MissingExponent));
+}
+
+#[test]
+fn a_fraction_needs_digits() {
+    assert_eq!(
+        tokens("1.;"),
+        [
+            TokenType::Number(1.0),
+            TokenType::Dot,
+            TokenType::Semicolon,
+            TokenType::EOF
+        ]
+    );
+}
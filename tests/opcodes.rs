@@ -0,0 +1,26 @@
+extern crate lox;
+
+use lox::op::OpCode;
+use std::convert::TryFrom;
+
+#[test]
+fn opcodes_round_trip_through_bytes() {
+    for &opcode in OpCode::ALL {
+        assert_eq!(OpCode::try_from(opcode as u8), Ok(opcode));
+    }
+
+    let known = OpCode::ALL.len() as u8;
+
+    for byte in known..=u8::MAX {
+        assert_eq!(OpCode::try_from(byte), Err(byte));
+    }
+}
+
+#[test]
+fn opcode_names_are_unique() {
+    for (i, a) in OpCode::ALL.iter().enumerate() {
+        for b in &OpCode::ALL[i + 1..] {
+            assert_ne!(a.name(), b.name());
+        }
+    }
+}
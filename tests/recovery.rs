@@ -0,0 +1,71 @@
+extern crate lox;
+
+use lox::{Error, VM};
+
+/// The line and code of each error compiling the input
+fn errors(input: &str) -> Vec<(u32, &'static str)> {
+    match VM::new().eval(input) {
+        Err(Error::Compile(reporter)) => reporter
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| {
+                let line = diagnostic.span().start.line;
+                (line, diagnostic.error_code().unwrap_or(""))
+            })
+            .collect(),
+        other => panic!("Expected a compile error, got {:?}", other),
+    }
+}
+
+#[test]
+fn recovers_at_a_keyword_the_error_was_found_at() {
+    // A missing operand, then a missing variable name
+    assert_eq!(errors("1 +\nvar = 2;"), [(2, "E0014"), (2, "E0016")]);
+}
+
+#[test]
+fn reports_every_lexer_error() {
+    let input = "\"a\\q\";\n1__0;\n#;\nvar a = 0x;\n\"open";
+
+    assert_eq!(
+        errors(input),
+        [
+            (1, "E0005"),
+            (2, "E0008"),
+            (3, "E0012"),
+            (4, "E0009"),
+            (5, "E0001")
+        ]
+    );
+}
+
+#[test]
+fn lexer_errors_do_not_cascade() {
+    // The malformed number is the only error on its line
+    assert_eq!(
+        errors("var a = 1__0 + 2;\nvar b = 0b2;"),
+        [(1, "E0008"), (2, "E0007")]
+    );
+}
+
+#[test]
+fn reports_an_error_per_statement() {
+    let input = "var = 1;\n1 +;\nvar ok = 1;\n(2;\nimport 5;\n[1, 2;";
+
+    assert_eq!(
+        errors(input),
+        [
+            (1, "E0016"),
+            (2, "E0014"),
+            (4, "E0015"),
+            (5, "E0015"),
+            (6, "E0015")
+        ]
+    );
+}
+
+#[test]
+fn skips_the_rest_of_a_failed_statement() {
+    // Only the first error in a statement is reported
+    assert_eq!(errors("var a = 1 + + + ;\nvar b = 2;"), [(1, "E0014")]);
+}
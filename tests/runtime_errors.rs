@@ -0,0 +1,55 @@
+extern crate lox;
+
+use lox::{Error, RuntimeError, VM};
+use std::fs;
+
+fn runtime_error(vm: &mut VM, path: &str, input: &str) -> RuntimeError {
+    match vm.eval_file(path, input) {
+        Err(Error::Runtime(e)) => e,
+        other => panic!("Expected a runtime error, got {:?}", other),
+    }
+}
+
+#[test]
+fn errors_point_at_the_file_and_line() {
+    let e = runtime_error(&mut VM::new(), "main.lox", "1;\nnope;");
+
+    assert_eq!((&e.path[..], e.line), ("main.lox", 2));
+    assert_eq!(
+        e.to_string(),
+        "error: Undefined variable `nope`.\n  --> main.lox:2"
+    );
+}
+
+#[test]
+fn errors_in_natives_point_at_the_call() {
+    let mut vm = VM::new();
+
+    vm.define_native("fail", 0, |_, _| Err(RuntimeError::new("Failed.")));
+
+    let e = runtime_error(&mut vm, "main.lox", "\n\nfail();");
+
+    assert_eq!(
+        (&e.path[..], e.line, &e.msg[..]),
+        ("main.lox", 3, "Failed.")
+    );
+}
+
+#[test]
+fn errors_in_imported_modules_point_into_the_module() {
+    let dir = std::env::temp_dir().join(format!("lox-runtime-errors-{}", std::process::id()));
+    fs::create_dir_all(dir.join("lib")).unwrap();
+    fs::write(dir.join("lib/bad.lox"), "var y = 2;\nmissing;\n").unwrap();
+
+    let main = dir.join("main.lox");
+    let e = runtime_error(
+        &mut VM::new(),
+        main.to_str().unwrap(),
+        "var x = 1;\nimport \"lib/bad.lox\";",
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(e.line, 2);
+    assert_eq!(e.path, dir.join("lib/bad.lox").to_str().unwrap());
+}
@@ -0,0 +1,167 @@
+extern crate lox;
+
+use lox::{Error, VM};
+
+/// The codes of the errors compiling the input
+fn error_codes(input: &str) -> Vec<&'static str> {
+    match VM::new().eval(input) {
+        Err(Error::Compile(reporter)) => reporter
+            .diagnostics()
+            .iter()
+            .filter_map(|diagnostic| diagnostic.error_code())
+            .collect(),
+        other => panic!("Expected a compile error, got {:?}", other),
+    }
+}
+
+fn eval(input: &str) -> String {
+    VM::new().eval(input).unwrap().to_string()
+}
+
+fn runtime_error(input: &str) -> String {
+    match VM::new().eval(input) {
+        Err(Error::Runtime(e)) => e.msg,
+        other => panic!("Expected a runtime error, got {:?}", other),
+    }
+}
+
+#[test]
+fn interpolations_are_converted_to_strings() {
+    let mut vm = VM::new();
+
+    vm.eval("var name = \"lox\";\nvar xs = [1, \"a\"];")
+        .unwrap();
+
+    let value = vm
+        .eval("\"${name} has ${len(xs)} items: ${xs}, ${nil}${true}!\";")
+        .unwrap();
+
+    assert_eq!(value.to_string(), "lox has 2 items: [1, \"a\"], niltrue!");
+    assert_eq!(eval("\"${\"${1 + 1}\" + \"!\"}\";"), "2!");
+    assert_eq!(eval("\"${ {\"k\": 1}[\"k\"] }\";"), "1");
+}
+
+#[test]
+fn empty_interpolations_are_errors() {
+    assert_eq!(error_codes("\"${}\";"), ["E0004"]);
+}
+
+#[test]
+fn unclosed_interpolations_are_reported_once() {
+    assert_eq!(error_codes("\"${x\";"), ["E0001", "E0003"]);
+    assert_eq!(error_codes("\"${1 2}\";"), ["E0015"]);
+}
+
+#[test]
+fn split_needs_a_separator() {
+    let mut vm = VM::new();
+
+    assert_eq!(
+        vm.eval("\"a,b,,c\".split(\",\");").unwrap().to_string(),
+        "[\"a\", \"b\", \"\", \"c\"]"
+    );
+
+    match vm.eval("\"a b\".split(\"\");") {
+        Err(Error::Runtime(e)) => assert_eq!(
+            e.msg,
+            "`split` cannot split on an empty string, use `chars` instead."
+        ),
+        other => panic!("Expected a runtime error, got {:?}", other),
+    }
+}
+
+#[test]
+fn methods_transform_strings() {
+    assert_eq!(eval("\" Héllo \".trim().upper();"), "HÉLLO");
+    assert_eq!(eval("\"ÀB\".lower();"), "àb");
+    assert_eq!(eval("\"ab\".repeat(3);"), "ababab");
+    assert_eq!(eval("\"a-b-c\".replace(\"-\", \"+\");"), "a+b+c");
+    assert_eq!(
+        eval("\"héllo\".chars();"),
+        "[\"h\", \"é\", \"l\", \"l\", \"o\"]"
+    );
+}
+
+#[test]
+fn methods_count_in_chars() {
+    assert_eq!(eval("\"日本語\".len();"), "3");
+    assert_eq!(eval("\"日本語\".find(\"語\");"), "2");
+    assert_eq!(eval("\"日本語\".find(\"x\");"), "-1");
+    assert_eq!(eval("\"日本語\".substring(1, 3);"), "本語");
+    assert_eq!(eval("\"日本語\"[-1];"), "語");
+}
+
+#[test]
+fn methods_search_strings() {
+    assert_eq!(
+        eval("[\"abc\".contains(\"b\"), \"abc\".starts_with(\"ab\"), \"abc\".ends_with(\"b\")];"),
+        "[true, true, false]"
+    );
+}
+
+#[test]
+fn methods_check_their_arguments() {
+    assert_eq!(
+        runtime_error("\"abc\".substring(2, 5);"),
+        "`substring` range 2..5 is out of bounds for a string of length 3."
+    );
+    assert_eq!(
+        runtime_error("\"abc\".repeat(-1);"),
+        "`repeat` expected a non-negative whole number but got `-1`."
+    );
+    assert_eq!(
+        runtime_error("\"ab\".repeat(1e30);"),
+        "`repeat` count 1000000000000000000000000000000 makes a string longer than 16777216 bytes."
+    );
+    assert_eq!(
+        runtime_error("\"ab\".repeat(1e10);"),
+        "`repeat` count 10000000000 makes a string longer than 16777216 bytes."
+    );
+    assert_eq!(eval("\"\".repeat(1e30);"), "");
+    assert_eq!(
+        runtime_error("\"abc\".contains(1);"),
+        "`contains` expected a string but got `1`."
+    );
+    assert_eq!(
+        runtime_error("\"abc\".replace(\"\", \"x\");"),
+        "`replace` cannot replace an empty string."
+    );
+    assert_eq!(
+        runtime_error("\"abc\".nope();"),
+        "`abc` has no method `nope`."
+    );
+}
+
+#[test]
+fn escapes_are_unescaped() {
+    assert_eq!(
+        VM::new()
+            .eval("\"a\\tb\\nc\\\\ \\\"q\\\" \\${x} \\u{1F600}\\0\";")
+            .unwrap()
+            .try_str(),
+        Some("a\tb\nc\\ \"q\" ${x} \u{1F600}\0")
+    );
+}
+
+#[test]
+fn invalid_escapes_are_errors() {
+    assert_eq!(error_codes("\"a\\q\";"), ["E0005"]);
+    assert_eq!(error_codes("\"\\u{110000}\";"), ["E0006"]);
+    assert_eq!(error_codes("\"\\u{1234567}\";"), ["E0006"]);
+    assert_eq!(error_codes("\"\\u1234\";"), ["E0006"]);
+    // Each escape is reported
+    assert_eq!(error_codes("\"\\q \\w\";"), ["E0005", "E0005"]);
+}
+
+#[test]
+fn raw_and_multi_line_strings() {
+    assert_eq!(
+        VM::new().eval("r\"a\\n${x}\";").unwrap().try_str(),
+        Some("a\\n${x}")
+    );
+    assert_eq!(
+        VM::new().eval("\"one\ntwo\";").unwrap().try_str(),
+        Some("one\ntwo")
+    );
+    assert_eq!(error_codes("r\"open;"), ["E0001"]);
+}
@@ -0,0 +1,63 @@
+extern crate lox;
+
+use lox::trace::Tracer;
+use lox::VM;
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// A writer whose output can still be read once the vm owns it
+#[derive(Clone, Default)]
+struct Shared(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Shared {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+#[test]
+fn traces_instructions_with_the_stack() {
+    let out = Shared::default();
+    let mut vm = VM::new();
+
+    vm.trace(Some(Tracer::new(out.clone())));
+    vm.eval_file("main.lox", "var a = 1;\n-a;").unwrap();
+
+    let expected = "\
+main.lox:1 0000 CONSTANT            1 '1'
+main.lox:1 0002 DEFINE_GLOBAL       0 'a'            [1]
+main.lox:2 0004 GET_GLOBAL          0 'a'
+main.lox:2 0006 NEGATE                               [1]
+main.lox:2 0007 RETURN                               [-1]
+";
+
+    assert_eq!(out.text(), expected);
+}
+
+#[test]
+fn traces_only_the_named_file() {
+    let out = Shared::default();
+    let mut vm = VM::new();
+
+    vm.trace(Some(Tracer::new(out.clone()).only("other")));
+    vm.eval_file("main.lox", "1;").unwrap();
+    vm.eval_file("lib/other.lox", "nil;").unwrap();
+
+    assert_eq!(out.text(), "lib/other.lox:1 0000 NIL\nlib/other.lox:1 0001 RETURN                               [nil]\n");
+
+    vm.trace(None);
+    vm.eval_file("lib/other.lox", "nil;").unwrap();
+
+    assert_eq!(out.text().lines().count(), 2);
+}
@@ -0,0 +1,70 @@
+extern crate lox;
+
+use lox::error::Reporter;
+use lox::scanner::Lexer;
+use lox::token::{TokenType, TokenWithTrivia, TriviaKind};
+
+fn tokens(input: &str) -> Vec<TokenWithTrivia<'_>> {
+    Lexer::new(input, Reporter::new()).tokens().collect()
+}
+
+fn reconstruct(tokens: &[TokenWithTrivia]) -> String {
+    let mut output = String::new();
+
+    for token in tokens {
+        for trivia in token.leading.iter() {
+            output.push_str(trivia.text);
+        }
+
+        output.push_str(token.text);
+
+        for trivia in token.trailing.iter() {
+            output.push_str(trivia.text);
+        }
+    }
+
+    output
+}
+
+#[test]
+fn reconstructs_the_input_exactly() {
+    let inputs = [
+        "",
+        "   \n\t",
+        "var a = 1; // one\n/// doc\nvar b = \"${a + 1} é\\n\";\r\n/* a /* b */ */ b;\n",
+        "r\"raw\" {\"k\": [0x1_f, 1.5e3]}",
+        // Malformed input is kept as well
+        "var = \"open ${ # 1__0 /* open",
+    ];
+
+    for input in inputs.iter() {
+        assert_eq!(&reconstruct(&tokens(input)), input);
+    }
+}
+
+#[test]
+fn attaches_trivia_around_tokens() {
+    let found = tokens("  a // note\n  b");
+
+    assert_eq!(found.len(), 3);
+
+    assert_eq!(found[0].token.value.ty, TokenType::Ident("a"));
+    assert_eq!(found[0].leading[0].kind, TriviaKind::Whitespace);
+    assert_eq!(
+        found[0]
+            .trailing
+            .iter()
+            .map(|trivia| (trivia.kind, trivia.text))
+            .collect::<Vec<_>>(),
+        [
+            (TriviaKind::Whitespace, " "),
+            (TriviaKind::Comment, "// note")
+        ]
+    );
+
+    // The newline starts the next token's leading trivia
+    assert_eq!(found[1].token.value.ty, TokenType::Ident("b"));
+    assert_eq!(found[1].leading[0].kind, TriviaKind::Newline);
+    assert_eq!(found[2].token.value.ty, TokenType::EOF);
+    assert_eq!(found[2].text, "");
+}
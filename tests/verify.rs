@@ -0,0 +1,138 @@
+extern crate lox;
+
+use lox::bytecode::{LoadError, MAGIC, VERSION};
+use lox::op::opcode;
+use lox::verify::VerifyError;
+use lox::{Error, VM};
+
+/// Encodes a `.loxc` file of the code, each byte on line 1, and the encoded constants
+fn loxc(code: &[u8], constants: &[&[u8]]) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(code.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(code);
+
+    for _ in code {
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+    }
+
+    bytes.extend_from_slice(&(constants.len() as u32).to_le_bytes());
+
+    for constant in constants {
+        bytes.extend_from_slice(constant);
+    }
+
+    bytes
+}
+
+const NUMBER: &[u8] = &[2, 0, 0, 0x80, 0x3f];
+const NAME: &[u8] = &[3, 1, 0, 0, 0, b'a'];
+
+fn verify_error(code: &[u8], constants: &[&[u8]]) -> VerifyError {
+    match VM::new().disassemble_bytecode("test.loxc", &loxc(code, constants)) {
+        Err(Error::Load(LoadError::Invalid(e))) => e,
+        other => panic!("Expected the chunk to be invalid, got {:?}", other),
+    }
+}
+
+fn rejects(code: &[u8], constants: &[&[u8]], offset: usize, msg: &str) {
+    let error = verify_error(code, constants);
+
+    assert_eq!((error.offset, &error.msg[..]), (offset, msg));
+}
+
+#[test]
+fn accepts_well_formed_chunks() {
+    let code = [
+        opcode::CONSTANT,
+        0,
+        opcode::DEFINE_GLOBAL,
+        1,
+        opcode::GET_GLOBAL,
+        1,
+        opcode::RETURN,
+    ];
+    let bytes = loxc(&code, &[NUMBER, NAME]);
+
+    assert!(VM::new().disassemble_bytecode("test.loxc", &bytes).is_ok());
+    assert_eq!(
+        VM::new()
+            .eval_bytecode("test.loxc", &bytes)
+            .unwrap()
+            .try_number(),
+        Some(1.0)
+    );
+}
+
+#[test]
+fn rejects_constants_out_of_bounds() {
+    rejects(
+        &[opcode::CONSTANT, 1, opcode::RETURN],
+        &[NUMBER],
+        0,
+        "Constant 1 is out of bounds of the 1 constants",
+    );
+}
+
+#[test]
+fn rejects_names_that_are_not_strings() {
+    rejects(
+        &[opcode::GET_GLOBAL, 0, opcode::RETURN],
+        &[NUMBER],
+        0,
+        "Constant 0 is not a name",
+    );
+}
+
+#[test]
+fn rejects_unknown_opcodes_and_missing_operands() {
+    rejects(&[0xff], &[], 0, "Unknown opcode 0xff");
+    rejects(
+        &[opcode::NIL, opcode::CONSTANT],
+        &[NUMBER],
+        1,
+        "Missing operands of opcode 0x01",
+    );
+}
+
+#[test]
+fn rejects_stack_underflow() {
+    rejects(
+        &[opcode::NIL, opcode::ADD, opcode::RETURN],
+        &[],
+        1,
+        "Pops 2 values with only 1 on the stack",
+    );
+    rejects(
+        &[opcode::NIL, opcode::BUILD_MAP, 1, opcode::RETURN],
+        &[],
+        1,
+        "Pops 2 values with only 1 on the stack",
+    );
+}
+
+#[test]
+fn rejects_stack_overflow() {
+    let mut code = vec![opcode::NIL; 255];
+    code.push(opcode::RETURN);
+
+    assert!(VM::new()
+        .disassemble_bytecode("test.loxc", &loxc(&code, &[]))
+        .is_ok());
+
+    code.insert(0, opcode::NIL);
+
+    rejects(&code, &[], 255, "Needs more than the 255 stack slots");
+}
+
+#[test]
+fn rejects_chunks_without_a_final_return() {
+    rejects(&[], &[], 0, "The chunk doesn't end with a return");
+    rejects(
+        &[opcode::NIL, opcode::RETURN, opcode::NIL],
+        &[],
+        3,
+        "The chunk doesn't end with a return",
+    );
+}